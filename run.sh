@@ -1,4 +1,4 @@
-./trello-vocab-loader --cred ../../example/trello_token.json --cfg ../../example/cfg.json --data ../../example/data.csv
+./trello-flow --cred examples/trello_cred.yml --tasks examples/task.yml run repeat
 
 
 export TELOXIDE_TOKEN=6142469372:AAH-xc7_V_KOB4Q4DRG9MabCKVxJ3laX-ds
@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use crate::{
+    err::FlowError,
+    executor::{ConfigurationFiles, Executor},
+    files::read_file_into_string,
+    task::{context, validate::validate},
+    telebot,
+    trello::{TrelloApi, TrelloConnector},
+};
+
+/// The files given by a flag, by an environment variable or found in the config directory.
+const FILES: [(&str, &str, &str); 4] = [
+    ("cred", "TRELLO_FLOW_CRED", "trello_cred.yml"),
+    ("tasks", "TRELLO_FLOW_TASKS", "task.yml"),
+    ("bot", "TRELLO_FLOW_BOT", "bot.yml"),
+    ("journal", "TRELLO_FLOW_JOURNAL", "journal"),
+];
+
+pub fn command() -> Command {
+    let files = FILES.iter().map(|(name, var, file)| {
+        Arg::new(*name)
+            .long(*name)
+            .global(true)
+            .value_name("PATH")
+            .help(format!(
+                "falls back to ${} and then to {} in the config directory",
+                var, file
+            ))
+    });
+    Command::new("trello-flow")
+        .about("Runs the flows of tasks over trello boards")
+        .subcommand_required(true)
+        .args(files)
+        .subcommand(
+            Command::new("run")
+                .about("run a task")
+                .arg(Arg::new("task").required(true))
+                .arg(
+                    Arg::new("args")
+                        .value_name("NAME=VALUE")
+                        .num_args(0..)
+                        .help("the arguments substituted into ~~name~~ in the tasks"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("print the changes instead of making them"),
+                ),
+        )
+        .subcommand(Command::new("tasks").about("list the tasks"))
+        .subcommand(
+            Command::new("validate")
+                .about("check the fields and the steps of the tasks without calling trello")
+                .arg(
+                    Arg::new("deep")
+                        .long("deep")
+                        .action(ArgAction::SetTrue)
+                        .help("check the columns and the labels against the board as well"),
+                ),
+        )
+        .subcommand(Command::new("boards").about("list the open boards"))
+        .subcommand(
+            Command::new("lists")
+                .about("list the columns of a board")
+                .arg(Arg::new("board").help("the board of the tasks file by default")),
+        )
+        .subcommand(
+            Command::new("revert")
+                .about("revert the changes of a run")
+                .arg(Arg::new("run").required(true)),
+        )
+        .subcommand(Command::new("bot").about("start the telegram bot"))
+}
+
+/// `$XDG_CONFIG_HOME/trello-flow` or `~/.config/trello-flow`.
+pub fn config_dir() -> PathBuf {
+    env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var("HOME")
+                .ok()
+                .map(|h| PathBuf::from(h).join(".config"))
+        })
+        .unwrap_or_default()
+        .join("trello-flow")
+}
+
+fn resolve<E>(matches: &ArgMatches, env: E, dir: &Path) -> Vec<String>
+where
+    E: Fn(&str) -> Option<String>,
+{
+    FILES
+        .iter()
+        .map(|(name, var, file)| {
+            matches
+                .get_one::<String>(name)
+                .cloned()
+                .or_else(|| env(var))
+                .unwrap_or_else(|| dir.join(file).to_string_lossy().to_string())
+        })
+        .collect()
+}
+
+pub fn configuration(matches: &ArgMatches) -> Result<ConfigurationFiles, FlowError> {
+    let mut files = resolve(matches, |v| env::var(v).ok(), &config_dir()).into_iter();
+    let mut next = || files.next().unwrap_or_default();
+    ConfigurationFiles::new(next(), next(), next(), next())
+}
+
+/// Parses `name=value` pairs.
+pub fn arguments<'a, I>(args: I) -> Result<HashMap<String, String>, FlowError>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    args.into_iter()
+        .map(|a| {
+            a.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .ok_or_else(|| {
+                    FlowError::UnexpectedValueError(format!(
+                        "the argument {} should look like name=value",
+                        a
+                    ))
+                })
+        })
+        .collect()
+}
+
+pub fn execute(matches: ArgMatches) -> Result<(), FlowError> {
+    let cfg = configuration(&matches)?;
+    match matches.subcommand() {
+        Some(("run", m)) => {
+            let task = m.get_one::<String>("task").cloned().unwrap_or_default();
+            let args = arguments(m.get_many::<String>("args").into_iter().flatten())?;
+            let mut executor = Executor::from(cfg, args)?;
+            let res = executor.start(task, m.get_flag("dry-run"))?;
+            println!("{}", res);
+            if let Some(run) = executor.last_run() {
+                println!("the changes can be reverted with: revert {}", run);
+            }
+        }
+        Some(("tasks", _)) => {
+            let ctx = context::from_str(&read_file_into_string(&cfg.tasks)?, HashMap::new())?;
+            let mut tasks: Vec<String> = ctx.tasks.into_keys().collect();
+            tasks.sort();
+            println!("{}", tasks.join("\n"));
+        }
+        Some(("validate", m)) => {
+            let ctx = context::from_str(&read_file_into_string(&cfg.tasks)?, HashMap::new())?;
+            let mut issues = validate(&ctx);
+            if m.get_flag("deep") {
+                issues.extend(Executor::from(cfg, HashMap::new())?.validate_board()?);
+            }
+            for issue in issues.iter() {
+                let level = if issue.is_error() { "error" } else { "warning" };
+                println!("{}: {}", level, issue);
+            }
+            let errors = issues.iter().filter(|i| i.is_error()).count();
+            if errors > 0 {
+                return Err(FlowError::UnexpectedValueError(format!(
+                    "{} problems are found in the tasks",
+                    errors
+                )));
+            }
+            println!(
+                "{} tasks of the board {} are valid",
+                ctx.tasks.len(),
+                ctx.board
+            );
+        }
+        Some(("boards", _)) => {
+            for b in TrelloConnector::from_file(&cfg.trello)?.boards()? {
+                println!("{}", b.name);
+            }
+        }
+        Some(("lists", m)) => {
+            let board = match m.get_one::<String>("board") {
+                Some(b) => b.clone(),
+                None => {
+                    context::from_str(&read_file_into_string(&cfg.tasks)?, HashMap::new())?.board
+                }
+            };
+            let connector = TrelloConnector::from_file(&cfg.trello)?;
+            let board = connector
+                .boards()?
+                .into_iter()
+                .find(|b| b.name == board)
+                .ok_or_else(|| {
+                    FlowError::ProcessingError(format!("the board {} is not found", board))
+                })?;
+            for l in connector.lists(&board.id)? {
+                println!("{}", l.name);
+            }
+        }
+        Some(("revert", m)) => {
+            let run = m.get_one::<String>("run").cloned().unwrap_or_default();
+            let res = Executor::from(cfg, HashMap::new())?.revert(&run)?;
+            println!("{}", res);
+        }
+        Some(("bot", _)) => {
+            tokio::runtime::Runtime::new()?.block_on(telebot::start(cfg))?;
+        }
+        _ => unreachable!("the subcommand is required"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use super::{arguments, command, resolve};
+
+    #[test]
+    fn files() {
+        let matches = command()
+            .try_get_matches_from(["trello-flow", "--tasks", "my_tasks.yml", "tasks"])
+            .unwrap();
+        let env = HashMap::from([("TRELLO_FLOW_CRED", "/env/cred.yml")]);
+
+        let files = resolve(
+            &matches,
+            |v| env.get(v).map(ToString::to_string),
+            &PathBuf::from("/cfg"),
+        );
+        assert_eq!(
+            files,
+            vec![
+                "/env/cred.yml",
+                "my_tasks.yml",
+                "/cfg/bot.yml",
+                "/cfg/journal"
+            ]
+        );
+    }
+
+    #[test]
+    fn run() {
+        let matches = command()
+            .try_get_matches_from(["trello-flow", "run", "new", "name=collapse", "--dry-run"])
+            .unwrap();
+        let (_, run) = matches.subcommand().unwrap();
+        assert_eq!(run.get_one::<String>("task").unwrap(), "new");
+        assert!(run.get_flag("dry-run"));
+
+        let args = arguments(run.get_many::<String>("args").unwrap()).unwrap();
+        assert_eq!(args["name"], "collapse");
+
+        assert!(arguments(&["name".to_string()]).is_err());
+        assert!(command().try_get_matches_from(["trello-flow"]).is_err());
+    }
+}
@@ -1,15 +1,64 @@
-use std::io::{self, Error};
+use std::{
+    fmt::{self, Display, Formatter},
+    io::Error,
+};
 
 use yaml_rust::ScanError;
 
+/// A field of the tasks file that is absent or can not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// the path of the field starting with the task, like `_move_to_repeat.params.to.column`
+    pub path: String,
+    pub msg: String,
+    /// the line and the column of the field or of the closest enclosing one
+    pub mark: Option<(usize, usize)>,
+}
+
+impl FieldError {
+    pub fn new(path: &str, msg: String) -> Self {
+        FieldError {
+            path: path.to_string(),
+            msg,
+            mark: None,
+        }
+    }
+
+    /// the task the field belongs to
+    pub fn task(&self) -> Option<&str> {
+        self.path.split_once(['.', '[']).map(|(task, _)| task)
+    }
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(task) = self.task() {
+            write!(f, "the task '{}', ", task)?;
+        }
+        write!(f, "{}", self.path)?;
+        if let Some((line, col)) = self.mark {
+            write!(f, " (line {}, column {})", line, col)?;
+        }
+        write!(f, ": {}", self.msg)
+    }
+}
+
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum FlowError {
     EnvError(Error),
     ParseError(ScanError),
     SerdeError(String),
-    NoFieldError(String),
+    /// an absent field of the tasks file, the optional fields fall back to defaults on it
+    NoFieldError(FieldError),
+    /// a field of the tasks file with a wrong type or value
+    FieldError(FieldError),
     UnexpectedValueError(String),
     ProcessingError(String),
+    /// the status code and the body of a failed response
+    HttpError(u16, String),
+    TransportError(String),
+    JsonError(String),
 }
 
 impl From<Error> for FlowError {
@@ -25,11 +74,37 @@ impl From<ScanError> for FlowError {
 }
 impl From<serde_yaml::Error> for FlowError {
     fn from(value: serde_yaml::Error) -> Self {
-        FlowError::SerdeError(
-            value
-                .location()
-                .map(|l| format!("error in {}", l.index()))
-                .unwrap_or("error in serde".to_string()),
-        )
+        FlowError::SerdeError(value.to_string())
+    }
+}
+
+impl From<ureq::Error> for FlowError {
+    fn from(value: ureq::Error) -> Self {
+        match value {
+            ureq::Error::Status(code, resp) => {
+                FlowError::HttpError(code, resp.into_string().unwrap_or_default())
+            }
+            ureq::Error::Transport(t) => FlowError::TransportError(t.to_string()),
+        }
     }
 }
+
+impl Display for FlowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FlowError::EnvError(e) => write!(f, "{}", e),
+            FlowError::ParseError(e) => write!(f, "the yaml is malformed: {}", e),
+            FlowError::SerdeError(e) => write!(f, "{}", e),
+            FlowError::NoFieldError(e) | FlowError::FieldError(e) => write!(f, "{}", e),
+            FlowError::UnexpectedValueError(e) => write!(f, "{}", e),
+            FlowError::ProcessingError(e) => write!(f, "{}", e),
+            FlowError::HttpError(code, body) => {
+                write!(f, "trello responded with {}: {}", code, body)
+            }
+            FlowError::TransportError(e) => write!(f, "trello is not reachable: {}", e),
+            FlowError::JsonError(e) => write!(f, "the response of trello is not recognized: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FlowError {}
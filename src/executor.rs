@@ -1,125 +1,680 @@
+mod journal;
+mod plan;
 mod process;
+mod snapshot;
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
 
 use crate::{
     err::FlowError,
     files::read_file_into_string,
-    task::{context::TaskContext, *},
-    trello::{self, *},
+    task::{
+        context::TaskContext,
+        tasks::CopyField,
+        validate::{boards, validate_board, validate_boards, validate_task, Issue},
+        *,
+    },
+    trello::*,
 };
-use rand::{rngs::ThreadRng, Rng};
+use journal::{Entry, Journal};
+pub use plan::Operation;
+use rand::{rngs::StdRng, SeedableRng};
+use snapshot::Snapshot;
 
 #[derive(Clone, Debug)]
 pub struct ConfigurationFiles {
     pub trello: String,
     pub tasks: String,
     pub bot: String,
+    /// the directory keeping the journals of the runs
+    pub journal: String,
 }
 impl ConfigurationFiles {
     pub fn new(
         trello_cred: String,
         tasks: String,
         bot: String,
+        journal: String,
     ) -> Result<ConfigurationFiles, FlowError> {
         Ok(Self {
             trello: trello_cred,
             tasks,
             bot,
+            journal,
         })
     }
 }
 
-pub struct Executor {
+pub struct Executor<T: TrelloApi = TrelloConnector> {
+    /// the board of the running task, the one of the tasks file by default
     board_id: String,
-    args: HashMap<String, String>,
+    boards: Vec<Board>,
     ctx: TaskContext,
-    pub connector: TrelloConnector,
-    rand: ThreadRng,
+    pub connector: T,
+    rand: StdRng,
+    snapshot: Snapshot,
+    // the collected operations when the executor runs dry
+    plan: Option<Vec<Operation>>,
+    journal_dir: Option<PathBuf>,
+    journal: Option<Journal>,
+    last_run: Option<String>,
 }
 
 impl Executor {
-    pub fn tasks(&self) -> Vec<String> {
-        self.ctx.tasks.keys().map(Clone::clone).collect()
-    }
-
     pub fn from(
         cfg: ConfigurationFiles,
         arguments: HashMap<String, String>,
     ) -> Result<Executor, FlowError> {
-        Executor::from_files(cfg.trello.as_str(), cfg.tasks.as_str(), arguments)
+        let mut executor =
+            Executor::from_files(cfg.trello.as_str(), cfg.tasks.as_str(), arguments)?;
+        executor.journal_dir = Some(PathBuf::from(cfg.journal));
+        Ok(executor)
     }
     pub fn from_files(
         cred_file: &str,
         yml_file: &str,
         arguments: HashMap<String, String>,
     ) -> Result<Executor, FlowError> {
-        let ctx = context::from_str(read_file_into_string(yml_file)?.as_str(), arguments.clone())?;
+        let ctx = context::from_str(read_file_into_string(yml_file)?.as_str(), arguments)?;
         let connector = TrelloConnector::from_file(cred_file)?;
-        Executor::new(ctx, connector, arguments)
+        Executor::new(ctx, connector)
     }
+}
 
-    fn new(
-        ctx: TaskContext,
-        connector: TrelloConnector,
-        args: HashMap<String, String>,
-    ) -> Result<Executor, FlowError> {
-        let board_name = ctx.board.clone();
-        let board = connector
-            .boards()
-            .into_iter()
-            .find(|x| x.name == board_name)
-            .ok_or(error("board is not found".to_string()));
+impl<T: TrelloApi> Executor<T> {
+    pub fn tasks(&self) -> Vec<String> {
+        self.ctx.tasks.keys().cloned().collect()
+    }
+
+    fn new(ctx: TaskContext, connector: T) -> Result<Executor<T>, FlowError> {
+        let boards = connector.boards()?;
+        let board = find_board(&boards, &ctx.board);
+
+        let rand = ctx
+            .seed
+            .map(StdRng::seed_from_u64)
+            .unwrap_or_else(StdRng::from_entropy);
 
         Ok(Self {
             ctx,
-            board_id: board?.id,
-            args,
-            rand: rand::thread_rng(),
+            board_id: board?,
+            boards,
+            rand,
             connector,
+            snapshot: Snapshot::default(),
+            plan: None,
+            journal_dir: None,
+            journal: None,
+            last_run: None,
+        })
+    }
+
+    /// Checks the columns and the labels of the tasks against their boards,
+    /// the lists and the labels are fetched once for all the tasks of a board.
+    pub fn validate_board(&mut self) -> Result<Vec<Issue>, FlowError> {
+        let mut issues = validate_boards(&self.ctx, &self.boards);
+        for board in boards(&self.ctx) {
+            // the unknown boards are reported above
+            let Ok(board_id) = find_board(&self.boards, board) else {
+                continue;
+            };
+            let lists = self.snapshot.lists(&self.connector, &board_id)?.to_vec();
+            let labels = self.snapshot.labels(&self.connector, &board_id)?;
+            issues.extend(validate_board(&self.ctx, board, &lists, labels));
+        }
+        issues.sort();
+        Ok(issues)
+    }
+
+    /// The id of the last journaled run, it can be passed to `revert`.
+    pub fn last_run(&self) -> Option<String> {
+        self.last_run.clone()
+    }
+
+    /// Writes the change to the journal of the run at once.
+    fn record(&mut self, entry: Entry) -> Result<(), FlowError> {
+        match (self.journal.as_mut(), self.journal_dir.as_ref()) {
+            (Some(journal), Some(dir)) => journal.append(dir, entry),
+            _ => Ok(()),
+        }
+    }
+
+    /// Drops the loaded lists, labels and cards so the next steps read the board again.
+    pub fn refresh(&mut self) {
+        self.snapshot.clear()
+    }
+
+    fn list_by_name(&mut self, name: &str) -> Result<Option<List>, FlowError> {
+        let board_id = self.board_id.clone();
+        self.list_on_board(&board_id, name)
+    }
+
+    fn list_on_board(&mut self, board_id: &str, name: &str) -> Result<Option<List>, FlowError> {
+        Ok(self
+            .snapshot
+            .lists(&self.connector, board_id)?
+            .iter()
+            .find(|l| l.name == name)
+            .cloned())
+    }
+
+    fn labels(&mut self) -> Result<Vec<Label>, FlowError> {
+        Ok(self
+            .snapshot
+            .labels(&self.connector, &self.board_id)?
+            .to_vec())
+    }
+
+    fn label_by_name(&mut self, name: &str, case: bool) -> Result<Option<Label>, FlowError> {
+        let labels = self.snapshot.labels(&self.connector, &self.board_id)?;
+        Ok(find_label(labels, name, case).cloned())
+    }
+
+    fn board_cards(&mut self, board_id: &str) -> Result<Vec<Card>, FlowError> {
+        Ok(self.snapshot.cards(&self.connector, board_id)?.to_vec())
+    }
+
+    fn cards_in_list(&mut self, list_id: &str) -> Result<Vec<Card>, FlowError> {
+        let board_id = self.list_board(list_id);
+        let mut cards: Vec<Card> = self
+            .snapshot
+            .cards(&self.connector, &board_id)?
+            .iter()
+            .filter(|c| c.id_list == list_id)
+            .cloned()
+            .collect();
+        cards.sort_by(|l, r| l.pos.total_cmp(&r.pos));
+        Ok(cards)
+    }
+
+    fn archived_cards(&mut self, board_id: &str) -> Result<Vec<Card>, FlowError> {
+        Ok(self.snapshot.archived(&self.connector, board_id)?.to_vec())
+    }
+
+    /// The id of the board by its name.
+    fn board_by_name(&self, name: &str) -> Result<String, FlowError> {
+        find_board(&self.boards, name)
+    }
+
+    /// The board of the list among the loaded ones, the board of the running task otherwise.
+    fn list_board(&self, list_id: &str) -> String {
+        self.snapshot
+            .board_of_list(list_id)
+            .unwrap_or(&self.board_id)
+            .to_string()
+    }
+
+    fn list_name(&mut self, list_id: &str) -> Result<String, FlowError> {
+        self.snapshot.lists(&self.connector, &self.board_id)?;
+        Ok(self
+            .snapshot
+            .list(list_id)
+            .map(|l| l.name.clone())
+            .unwrap_or_else(|| list_id.to_string()))
+    }
+
+    /// Copies the card with its name and the kept fields, the copy is journaled as created.
+    fn copy_card(
+        &mut self,
+        card: &Card,
+        list_id: &str,
+        pos: &str,
+        keep: &[CopyField],
+    ) -> Result<Card, FlowError> {
+        let copy = if self.plan.is_some() {
+            let to = self.list_name(list_id)?;
+            let copy = Card {
+                id: format!("planned-{}", self.plan.as_ref().map_or(0, Vec::len)),
+                pos: self.expected_pos(list_id, pos)?,
+                name: card.name.clone(),
+                desc: if keep.contains(&CopyField::Desc) {
+                    card.desc.clone()
+                } else {
+                    String::new()
+                },
+                id_list: list_id.to_string(),
+                id_board: self.list_board(list_id),
+                id_labels: if keep.contains(&CopyField::Labels) {
+                    card.id_labels.clone()
+                } else {
+                    vec![]
+                },
+                url: String::new(),
+                short_url: String::new(),
+                closed: false,
+                date_last_activity: None,
+                due: card.due.clone().filter(|_| keep.contains(&CopyField::Due)),
+            };
+            self.plan
+                .get_or_insert_with(Vec::new)
+                .push(Operation::Copy {
+                    card: card.name.clone(),
+                    to,
+                    pos: pos.to_string(),
+                    keep: keep.iter().map(|k| k.name().to_string()).collect(),
+                });
+            copy
+        } else {
+            let copy = self.connector.copy_card(card, list_id, pos, keep)?;
+            self.record(Entry::Created {
+                id: copy.id.clone(),
+            })?;
+            copy
+        };
+        self.snapshot.update(&copy);
+        Ok(copy)
+    }
+
+    /// The number trello would give to a position (top, bottom or a number) in the list.
+    fn expected_pos(&mut self, list_id: &str, pos: &str) -> Result<f32, FlowError> {
+        let cards = self.cards_in_list(list_id)?;
+        Ok(match pos {
+            "top" => cards.first().map(|c| c.pos / 2.0).unwrap_or(65536.0),
+            "bottom" => cards.last().map(|c| c.pos + 65536.0).unwrap_or(65536.0),
+            n => n
+                .parse()
+                .map_err(|_| error(format!("the position {} is not a number", n)))?,
         })
     }
-    pub fn start(&mut self, task: String) -> Result<State, FlowError> {
+
+    fn create_card(&mut self, list_id: &str, name: &str, pos: &str) -> Result<Card, FlowError> {
+        let card = if self.plan.is_some() {
+            let to = self.list_name(list_id)?;
+            let card = Card {
+                id: format!("planned-{}", self.plan.as_ref().map_or(0, Vec::len)),
+                pos: self.expected_pos(list_id, pos)?,
+                name: name.to_string(),
+                desc: String::new(),
+                id_list: list_id.to_string(),
+                id_board: self.list_board(list_id),
+                id_labels: vec![],
+                url: String::new(),
+                short_url: String::new(),
+                closed: false,
+                date_last_activity: None,
+                due: None,
+            };
+            self.plan
+                .get_or_insert_with(Vec::new)
+                .push(Operation::Create {
+                    card: name.to_string(),
+                    to,
+                    pos: pos.to_string(),
+                });
+            card
+        } else {
+            let card = self.connector.create_card(list_id, name, pos)?;
+            self.record(Entry::Created {
+                id: card.id.clone(),
+            })?;
+            card
+        };
+        self.snapshot.update(&card);
+        Ok(card)
+    }
+
+    /// Moves the card to the list, the list can be on another board.
+    fn mov_card(&mut self, card: &Card, list_id: &str, pos: &str) -> Result<Card, FlowError> {
+        let board = self.list_board(list_id);
+        let card = if self.plan.is_some() {
+            let from = self.list_name(&card.id_list)?;
+            let to = self.list_name(list_id)?;
+            let mut moved = card.clone();
+            moved.pos = self.expected_pos(list_id, pos)?;
+            moved.id_list = list_id.to_string();
+            moved.id_board = board;
+            self.plan
+                .get_or_insert_with(Vec::new)
+                .push(Operation::Move {
+                    card: card.name.clone(),
+                    from,
+                    to,
+                    pos: pos.to_string(),
+                });
+            moved
+        } else {
+            self.record(Entry::changed(card))?;
+            if board == card.id_board {
+                self.connector.mov_card(&card.id, list_id, pos)?
+            } else {
+                self.connector
+                    .mov_card_to_board(&card.id, &board, list_id, pos)?
+            }
+        };
+        self.snapshot.update(&card);
+        Ok(card)
+    }
+
+    fn archive_card(&mut self, card: &Card) -> Result<Card, FlowError> {
+        let card = if self.plan.is_some() {
+            let list = self.list_name(&card.id_list)?;
+            self.plan
+                .get_or_insert_with(Vec::new)
+                .push(Operation::Archive {
+                    card: card.name.clone(),
+                    list,
+                });
+            Card {
+                closed: true,
+                ..card.clone()
+            }
+        } else {
+            self.record(Entry::Archived {
+                id: card.id.clone(),
+            })?;
+            self.connector.archive_card(&card.id)?
+        };
+        self.snapshot.update(&card);
+        Ok(card)
+    }
+
+    fn unarchive_card(&mut self, card: &Card) -> Result<Card, FlowError> {
+        let card = if self.plan.is_some() {
+            let list = self.list_name(&card.id_list)?;
+            self.plan
+                .get_or_insert_with(Vec::new)
+                .push(Operation::Unarchive {
+                    card: card.name.clone(),
+                    list,
+                });
+            Card {
+                closed: false,
+                ..card.clone()
+            }
+        } else {
+            self.record(Entry::Unarchived {
+                id: card.id.clone(),
+            })?;
+            self.connector.unarchive_card(&card.id)?
+        };
+        self.snapshot.update(&card);
+        Ok(card)
+    }
+
+    fn delete_card(&mut self, card: &Card) -> Result<(), FlowError> {
+        if self.plan.is_some() {
+            let list = self.list_name(&card.id_list)?;
+            self.plan
+                .get_or_insert_with(Vec::new)
+                .push(Operation::Delete {
+                    card: card.name.clone(),
+                    list,
+                });
+        } else {
+            warn!("delete the card {} for good", card.name);
+            self.connector.delete_card(&card.id)?;
+        }
+        self.snapshot.remove(&card.id);
+        Ok(())
+    }
+
+    /// Sets the name and the description, only the changed ones are sent to trello.
+    fn rewrite_card(&mut self, card: &Card, name: &str, desc: &str) -> Result<Card, FlowError> {
+        if card.name == name && card.desc == desc {
+            return Ok(card.clone());
+        }
+        let card = if let Some(plan) = self.plan.as_mut() {
+            if card.name != name {
+                plan.push(Operation::Rename {
+                    card: card.name.clone(),
+                    name: name.to_string(),
+                });
+            }
+            if card.desc != desc {
+                plan.push(Operation::Describe {
+                    card: card.name.clone(),
+                    desc: desc.to_string(),
+                });
+            }
+            Card {
+                name: name.to_string(),
+                desc: desc.to_string(),
+                ..card.clone()
+            }
+        } else {
+            self.record(Entry::changed(card))?;
+            let mut updated = card.clone();
+            if card.name != name {
+                updated = self.connector.update_card_name(&card.id, name)?;
+            }
+            if card.desc != desc {
+                updated = self.connector.update_card_dsc(&card.id, desc)?;
+            }
+            updated
+        };
+        self.snapshot.update(&card);
+        Ok(card)
+    }
+
+    fn create_label(&mut self, name: &str, color: Option<&str>) -> Result<Label, FlowError> {
+        let label = if let Some(plan) = self.plan.as_mut() {
+            let id = format!("planned-{}", plan.len());
+            plan.push(Operation::CreateLabel {
+                label: name.to_string(),
+                color: color.map(ToString::to_string),
+            });
+            Label {
+                id,
+                name: name.to_string(),
+                color: color.map(ToString::to_string),
+            }
+        } else {
+            info!("create the label {}", name);
+            let label = self.connector.create_label(&self.board_id, name, color)?;
+            self.record(Entry::CreatedLabel {
+                id: label.id.clone(),
+            })?;
+            label
+        };
+        self.snapshot.add_label(&self.board_id, &label);
+        Ok(label)
+    }
+
+    fn add_label(&mut self, card: &Card, label: &Label) -> Result<Card, FlowError> {
+        if card.id_labels.contains(&label.id) {
+            return Ok(card.clone());
+        }
+        if let Some(plan) = self.plan.as_mut() {
+            plan.push(Operation::AddLabel {
+                card: card.name.clone(),
+                label: label.name.clone(),
+            });
+        } else {
+            self.record(Entry::Labeled {
+                id: card.id.clone(),
+                label: label.id.clone(),
+            })?;
+            self.connector.add_label(&card.id, &label.id)?;
+        }
+        let mut card = card.clone();
+        card.id_labels.push(label.id.clone());
+        self.snapshot.update(&card);
+        Ok(card)
+    }
+
+    fn remove_label(&mut self, card: &Card, label: &Label) -> Result<Card, FlowError> {
+        if !card.id_labels.contains(&label.id) {
+            return Ok(card.clone());
+        }
+        if let Some(plan) = self.plan.as_mut() {
+            plan.push(Operation::RemoveLabel {
+                card: card.name.clone(),
+                label: label.name.clone(),
+            });
+        } else {
+            self.record(Entry::Unlabeled {
+                id: card.id.clone(),
+                label: label.id.clone(),
+            })?;
+            self.connector.remove_label(&card.id, &label.id)?;
+        }
+        let mut card = card.clone();
+        card.id_labels.retain(|l| *l != label.id);
+        self.snapshot.update(&card);
+        Ok(card)
+    }
+
+    /// Runs the task. In a dry run the board is read but not changed,
+    /// the changes are returned as a plan.
+    /// Otherwise the changes are journaled if the journal directory is set.
+    pub fn start(&mut self, task: String, dry_run: bool) -> Result<State, FlowError> {
+        let errors: Vec<String> = validate_task(&self.ctx, &task)
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        if !errors.is_empty() {
+            return Err(error(format!(
+                "the tasks are not valid: {}",
+                errors.join(", ")
+            )));
+        }
+        if dry_run {
+            self.plan = Some(vec![]);
+            let res = self.run(&task);
+            let plan = self.plan.take().unwrap_or_default();
+            // the planned cards and labels do not exist on the board
+            self.snapshot.clear();
+            res.map(|_| State::Plan(plan))
+        } else if self.journal_dir.is_some() {
+            self.journal = Some(Journal::new(&task));
+            let res = self.run(&task);
+            if let Some(journal) = self.journal.take() {
+                if !journal.entries.is_empty() {
+                    info!("the changes are journaled as the run {}", journal.run);
+                    self.last_run = Some(journal.run);
+                }
+            }
+            res
+        } else {
+            self.run(&task)
+        }
+    }
+
+    /// Takes back the changes of a journaled run in the reverse order:
+    /// the changed cards get their list, position, name and description back,
+    /// the created cards are archived, the archived ones are unarchived
+    /// the added and removed labels are taken off and put back and the created labels are deleted.
+    /// The deleted cards are not journaled as they cannot be brought back.
+    pub fn revert(&mut self, run: &str) -> Result<State, FlowError> {
+        let dir = self
+            .journal_dir
+            .clone()
+            .ok_or_else(|| error("the journal directory is not set".to_string()))?;
+        let journal = Journal::load(&dir, run)?;
+        info!("revert the run {} of the task {}", run, journal.task);
+
+        let mut cards = vec![];
+        for entry in journal.entries.iter().rev() {
+            // the label changes are reverted without reading the card back
+            let card = match entry {
+                Entry::Labeled { id, label } => {
+                    self.connector.remove_label(id, label)?;
+                    self.snapshot.clear();
+                    continue;
+                }
+                Entry::Unlabeled { id, label } => {
+                    self.connector.add_label(id, label)?;
+                    self.snapshot.clear();
+                    continue;
+                }
+                Entry::CreatedLabel { id } => {
+                    self.connector.delete_label(id)?;
+                    self.snapshot.clear();
+                    continue;
+                }
+                Entry::Created { id } | Entry::Unarchived { id } => {
+                    self.connector.archive_card(id)?
+                }
+                Entry::Archived { id } => self.connector.unarchive_card(id)?,
+                Entry::Changed {
+                    id,
+                    id_list,
+                    id_board,
+                    pos,
+                    name,
+                    desc,
+                } => {
+                    let pos = pos.to_string();
+                    let mut card = if id_board.is_empty() {
+                        self.connector.mov_card(id, id_list, &pos)?
+                    } else {
+                        self.connector
+                            .mov_card_to_board(id, id_board, id_list, &pos)?
+                    };
+                    if card.name != *name {
+                        card = self.connector.update_card_name(id, name)?;
+                    }
+                    if card.desc != *desc {
+                        card = self.connector.update_card_dsc(id, desc)?;
+                    }
+                    card
+                }
+            };
+            self.snapshot.update(&card);
+            cards.push(card);
+        }
+        Ok(State::Pipe(cards))
+    }
+
+    fn run(&mut self, task: &str) -> Result<State, FlowError> {
         let task = self
             .ctx
             .tasks
-            .get(&task)
-            .map(Clone::clone)
-            .ok_or(error(format!("a task {} is not found", task)))?;
+            .get(task)
+            .cloned()
+            .ok_or_else(|| error(format!("a task {} is not found", task)))?;
 
         info!("the executor starts a task: {:?}", task);
-        task.body.process(self, State::Init)
+        task.process(self, State::Init)
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum State {
     Pipe(Vec<Card>),
+    Plan(Vec<Operation>),
     Init,
     End,
 }
 
-impl ToString for State {
-    fn to_string(&self) -> String {
+impl Display for State {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             State::Pipe(e) => {
                 let c_names: Vec<String> = e
-                    .into_iter()
+                    .iter()
                     .map(|c| format!("{} {}", c.name, c.short_url))
                     .collect();
                 let c_names = c_names.join("\n");
                 if c_names.is_empty() {
-                    "no cards found".to_string()
+                    write!(f, "no cards found")
                 } else {
-                    c_names
+                    write!(f, "{}", c_names)
                 }
             }
-            State::Init => "init".to_string(),
-            State::End => "end".to_string(),
+            State::Plan(ops) if ops.is_empty() => write!(f, "nothing to change"),
+            State::Plan(ops) => {
+                let ops: Vec<String> = ops.iter().map(ToString::to_string).collect();
+                write!(f, "{}", ops.join("\n"))
+            }
+            State::Init => write!(f, "init"),
+            State::End => write!(f, "end"),
         }
     }
 }
 
+fn find_board(boards: &[Board], name: &str) -> Result<String, FlowError> {
+    boards
+        .iter()
+        .find(|b| b.name == name)
+        .map(|b| b.id.clone())
+        .ok_or_else(|| error(format!("the board {} is not found", name)))
+}
+
 pub fn error(mes: String) -> FlowError {
     FlowError::ProcessingError(mes)
 }
@@ -134,33 +689,432 @@ impl State {
             ))),
         }
     }
-
-    pub fn upd_cards(&self, cards: Vec<Card>) -> State {
-        match self {
-            State::Pipe(elems) => State::Pipe(cards),
-            State::Init => State::Pipe(cards),
-            State::End => State::Pipe(cards),
-        }
-    }
 }
 
 trait TaskProcessor {
-    fn process(&self, executor: &mut Executor, state: State) -> Result<State, FlowError>;
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError>;
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Executor;
+    use std::{
+        collections::{HashMap, HashSet},
+        env, fs,
+        path::PathBuf,
+        process,
+    };
+
+    use super::{Executor, Operation, State};
+    use crate::{
+        files::read_file_into_string,
+        task::context,
+        trello::{fake::FakeBoard, TrelloApi},
+    };
+
+    /// A journal directory of its own for the test, it should be removed at the end.
+    pub fn journal_dir(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("trello-flow-{}-{}", test, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    pub fn eng() -> FakeBoard {
+        let mut board = FakeBoard::new("ENG")
+            .list("Later")
+            .list("Repeating")
+            .list("Idioms")
+            .list("Archive")
+            .colored_label("Demand", Some("green"))
+            .colored_label("Mispronounced", Some("red"))
+            .label("Later");
+        for i in 0..30 {
+            let labels: &[&str] = match i % 3 {
+                0 => &["Demand"],
+                1 => &["Mispronounced"],
+                _ => &[],
+            };
+            board = board.card("Archive", &format!("word {}", i), labels);
+        }
+        for i in 0..5 {
+            board = board.card("Idioms", &format!("idiom {}", i), &[]);
+        }
+        board
+    }
 
     #[test]
     fn base_test() {
-        let mut e = Executor::from_files(
-            "/home/besok/projects/trello-flow/examples/trello_cred.yml",
-            "/home/besok/projects/trello-flow/examples/task.yml",
+        let ctx = context::from_str(
+            read_file_into_string("examples/task.yml").unwrap().as_str(),
+            Default::default(),
+        )
+        .unwrap();
+        let mut e = Executor::new(ctx, eng()).unwrap();
+
+        let r = e.start("repeat".to_string(), false).unwrap();
+        assert!(matches!(r, State::End));
+        let repeating = e.connector.card_names("Repeating").len();
+        assert!(repeating >= 10);
+        assert_eq!(e.connector.card_names("Archive").len(), 30 - repeating);
+        assert_eq!(e.connector.card_names("Idioms").len(), 5);
+        assert_eq!(e.connector.cards(&e.board_id).unwrap().len(), 35);
+    }
+
+    #[test]
+    fn snapshot_test() {
+        let ctx = context::from_str(
+            read_file_into_string("examples/task.yml").unwrap().as_str(),
+            Default::default(),
+        )
+        .unwrap();
+        let mut e = Executor::new(ctx, eng()).unwrap();
+
+        e.start("repeat".to_string(), false).unwrap();
+        // boards, lists, labels and cards are read once
+        assert_eq!(e.connector.reads(), 4);
+
+        let archive = e.list_by_name("Archive").unwrap().unwrap();
+        assert_eq!(
+            e.cards_in_list(&archive.id).unwrap().len(),
+            e.connector.card_names("Archive").len()
+        );
+    }
+
+    #[test]
+    fn refresh_test() {
+        let ctx = context::from_str("board: ENG", Default::default()).unwrap();
+        let mut e = Executor::new(ctx, eng()).unwrap();
+        let later = e.list_by_name("Later").unwrap().unwrap();
+        assert!(e.cards_in_list(&later.id).unwrap().is_empty());
+
+        e.connector
+            .create_card(&later.id, "collapse", "top")
+            .unwrap();
+        assert!(e.cards_in_list(&later.id).unwrap().is_empty());
+
+        e.refresh();
+        assert_eq!(e.cards_in_list(&later.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn dry_run_test() {
+        let ctx = context::from_str(
+            read_file_into_string("examples/task.yml").unwrap().as_str(),
+            Default::default(),
+        )
+        .unwrap();
+        let mut e = Executor::new(ctx, eng()).unwrap();
+
+        let plan = match e.start("clean_rep".to_string(), true).unwrap() {
+            State::Plan(plan) => plan,
+            s => panic!("expected a plan, got {:?}", s),
+        };
+        assert!(plan.is_empty());
+
+        let plan = match e.start("repeat".to_string(), true).unwrap() {
+            State::Plan(plan) => plan,
+            s => panic!("expected a plan, got {:?}", s),
+        };
+        assert!(plan.len() >= 10);
+        // the shuffled idioms are moved within their column
+        assert!(plan.iter().all(|op| matches!(
+            op,
+            Operation::Move { from, to, pos, .. }
+                if from == "Archive" && to == "Repeating" && pos == "top"
+                    || from == "Idioms" && to == "Idioms"
+        )));
+        // the planned moves are seen by the following steps but trello is not changed
+        let moved: HashSet<&Operation> = plan.iter().collect();
+        assert_eq!(moved.len(), plan.len());
+        assert!(e.connector.card_names("Repeating").is_empty());
+        assert_eq!(e.connector.card_names("Archive").len(), 30);
+        assert_eq!(
+            State::Plan(plan[..1].to_vec()).to_string(),
+            plan[0].to_string()
+        );
+        assert!(plan[0].to_string().starts_with("move 'word "));
+    }
+
+    #[test]
+    fn dry_run_then_run_test() {
+        let ctx = context::from_str(
+            read_file_into_string("examples/task.yml").unwrap().as_str(),
+            Default::default(),
+        )
+        .unwrap();
+        let mut e = Executor::new(ctx, eng()).unwrap();
+        assert!(matches!(
+            e.start("repeat".to_string(), true).unwrap(),
+            State::Plan(_)
+        ));
+
+        // the planned moves are dropped, the run starts from the board as it is
+        e.start("repeat".to_string(), false).unwrap();
+        let repeating = e.connector.card_names("Repeating");
+        assert!(repeating.len() >= 10);
+        assert_eq!(
+            e.connector.card_names("Archive").len(),
+            30 - repeating.len()
+        );
+        for list in ["Repeating", "Archive", "Idioms"] {
+            let id = e.list_by_name(list).unwrap().unwrap().id;
+            let mut cards = e.cards_in_list(&id).unwrap();
+            cards.sort_by(|l, r| l.pos.total_cmp(&r.pos));
+            let names: Vec<String> = cards.into_iter().map(|c| c.name).collect();
+            assert_eq!(names, e.connector.card_names(list));
+        }
+    }
+
+    #[test]
+    fn journal_test() {
+        let ctx = context::from_str(
+            read_file_into_string("examples/task.yml").unwrap().as_str(),
+            HashMap::from([("name".to_string(), "collapse".to_string())]),
+        )
+        .unwrap();
+        let mut e = Executor::new(ctx, eng()).unwrap();
+        let dir = journal_dir("journal");
+        e.journal_dir = Some(dir.clone());
+        let archive = e.connector.card_names("Archive");
+
+        e.start("repeat".to_string(), false).unwrap();
+        let repeat = e.last_run().unwrap();
+        e.start("new".to_string(), false).unwrap();
+        let new = e.last_run().unwrap();
+        assert_ne!(repeat, new);
+        assert_eq!(e.connector.card_names("Later"), vec!["collapse"]);
+        assert!(!e.connector.card_names("Repeating").is_empty());
+
+        e.revert(&new).unwrap();
+        assert!(e.connector.card_names("Later").is_empty());
+
+        let reverted = e.revert(&repeat).unwrap().cards().unwrap();
+        assert!(reverted.len() >= 10);
+        assert!(e.connector.card_names("Repeating").is_empty());
+        assert_eq!(e.connector.card_names("Archive"), archive);
+
+        assert!(e.revert("unknown").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn journal_failed_run_test() {
+        let ctx = context::from_str(
+            r#"
+            board: ENG
+            take_2:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+                    size: 2
+            move_later:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Later
+            move_nowhere:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Done
+            flow:
+                type: flow
+                params:
+                    - take_2
+                    - move_later
+                    - move_nowhere
+        "#,
+            Default::default(),
+        )
+        .unwrap();
+        let mut e = Executor::new(ctx, eng()).unwrap();
+        let dir = journal_dir("failed-run");
+        e.journal_dir = Some(dir.clone());
+
+        assert!(e.start("flow".to_string(), false).is_err());
+        assert_eq!(e.connector.card_names("Later"), ["word 1", "word 0"]);
+        // the moves made before the failure are journaled and can be taken back
+        e.revert(&e.last_run().unwrap()).unwrap();
+        assert!(e.connector.card_names("Later").is_empty());
+        assert_eq!(e.connector.card_names("Archive").len(), 30);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_board() {
+        let ctx = context::from_str("board: FR", Default::default()).unwrap();
+        assert!(Executor::new(ctx, eng()).is_err());
+    }
+
+    #[test]
+    fn validate_board_test() {
+        let ctx = context::from_str(
+            r#"
+            board: ENG
+            take:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Idiom
+            filter:
+                type: filter
+                params:
+                    by: label
+                    rhs: demand
+            strict_filter:
+                type: filter
+                params:
+                    by: label
+                    rhs: demand
+                    case: false
+            move:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Done
+        "#,
             Default::default(),
         )
         .unwrap();
+        let mut executor = Executor::new(ctx, eng()).unwrap();
+        let issues: Vec<String> = executor
+            .validate_board()
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "the task move refers to the unknown column Done",
+                "the task take refers to the unknown column Idiom, did you mean Idioms?",
+                "the task strict_filter refers to the unknown label demand, did you mean Demand?",
+            ]
+        );
+        assert_eq!(executor.connector.reads(), 3);
+    }
 
-        let r = e.start("repeat".to_string()).unwrap();
+    #[test]
+    fn validate_boards_test() {
+        let ctx = context::from_str(
+            r#"
+            board: ENG
+            take:
+                board: Reading
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Idioms
+            to_reading:
+                type: action
+                params:
+                    type: move
+                    to:
+                        board: Reading
+                        column: Don
+            to_eng:
+                board: Reading
+                type: action
+                params:
+                    type: copy
+                    to:
+                        board: ENG
+                        column: Later
+            from_typo:
+                type: take
+                params:
+                    from:
+                        type: board
+                        source: Readng
+            on_typo:
+                board: FR
+                type: filter
+                params:
+                    by: label
+                    rhs: Demand
+            read_flow:
+                board: Reading
+                type: flow
+                params:
+                    - _take_done
+                    - _to_later
+            _take_done:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Done
+            _to_later:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Later
+        "#,
+            Default::default(),
+        )
+        .unwrap();
+        let board = eng().board("Reading").list("Inbox").list("Done");
+        let mut executor = Executor::new(ctx, board).unwrap();
+        let issues: Vec<String> = executor
+            .validate_board()
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "the task from_typo refers to the unknown board Readng, did you mean Reading?",
+                "the task on_typo refers to the unknown board FR",
+                "the task _to_later refers to the unknown column Later",
+                "the task take refers to the unknown column Idioms",
+                "the task to_reading refers to the unknown column Don, did you mean Done?",
+            ]
+        );
+        assert_eq!(executor.connector.reads(), 5);
+    }
+
+    #[test]
+    fn invalid_task() {
+        let ctx = context::from_str(
+            r#"
+            board: ENG
+            _take:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Idioms
+            _move:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Later
+            flow:
+                type: flow
+                params:
+                    - _take
+                    - _move
+                    - _prnt
+        "#,
+            Default::default(),
+        )
+        .unwrap();
+        let mut executor = Executor::new(ctx, eng()).unwrap();
+        assert!(executor.start("flow".to_string(), false).is_err());
+        assert!(executor.start("_move".to_string(), false).is_err());
+        assert_eq!(executor.connector.card_names("Idioms").len(), 5);
     }
 }
@@ -0,0 +1,184 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    err::FlowError,
+    files::{read_file_into_string, yml_str_to},
+    trello::Card,
+};
+
+/// A change made by a run, enough to take it back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Entry {
+    /// a card created by the run, reverted by archiving it
+    Created { id: String },
+    /// a card changed by the run with the values it had before
+    Changed {
+        id: String,
+        id_list: String,
+        /// absent in the journals written before the moves between boards
+        #[serde(default)]
+        id_board: String,
+        pos: f32,
+        name: String,
+        desc: String,
+    },
+    /// a card archived by the run, reverted by unarchiving it
+    Archived { id: String },
+    /// a card unarchived by the run, reverted by archiving it
+    Unarchived { id: String },
+    /// a label added to a card by the run, reverted by removing it
+    Labeled { id: String, label: String },
+    /// a label removed from a card by the run, reverted by adding it back
+    Unlabeled { id: String, label: String },
+    /// a label created by the run, reverted by deleting it
+    #[serde(rename = "created_label")]
+    CreatedLabel { id: String },
+}
+
+impl Entry {
+    pub fn changed(card: &Card) -> Self {
+        Entry::Changed {
+            id: card.id.clone(),
+            id_list: card.id_list.clone(),
+            id_board: card.id_board.clone(),
+            pos: card.pos,
+            name: card.name.clone(),
+            desc: card.desc.clone(),
+        }
+    }
+}
+
+/// The changes of one run of the executor, stored as `<run>.yml` in the journal directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Journal {
+    pub run: String,
+    pub task: String,
+    pub entries: Vec<Entry>,
+}
+
+/// The head of the journal file, the entries are appended after it one by one.
+#[derive(Serialize)]
+struct Head<'a> {
+    run: &'a str,
+    task: &'a str,
+}
+
+impl Journal {
+    /// The run is named by the current millisecond and a random suffix
+    /// so the runs started at the same time get their own files.
+    pub fn new(task: &str) -> Self {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        Journal {
+            run: format!("{}-{:04x}", millis, rand::random::<u16>()),
+            task: task.to_string(),
+            entries: vec![],
+        }
+    }
+
+    fn file(dir: &Path, run: &str) -> PathBuf {
+        dir.join(format!("{}.yml", run))
+    }
+
+    /// Writes the entry down at once so the changes made before a failure are kept.
+    /// The file is created with the first entry and is never overwritten.
+    pub fn append(&mut self, dir: &Path, entry: Entry) -> Result<(), FlowError> {
+        let mut file = if self.entries.is_empty() {
+            fs::create_dir_all(dir)?;
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(Journal::file(dir, &self.run))?;
+            let head = Head {
+                run: &self.run,
+                task: &self.task,
+            };
+            file.write_all(format!("{}entries:\n", serde_yaml::to_string(&head)?).as_bytes())?;
+            file
+        } else {
+            OpenOptions::new()
+                .append(true)
+                .open(Journal::file(dir, &self.run))?
+        };
+        file.write_all(serde_yaml::to_string(&[&entry])?.as_bytes())?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub fn load(dir: &Path, run: &str) -> Result<Journal, FlowError> {
+        let path = Journal::file(dir, run);
+        yml_str_to(read_file_into_string(&path.to_string_lossy())?.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{Entry, Journal};
+
+    #[test]
+    fn append_load() {
+        let dir = env::temp_dir().join(format!("trello-flow-journal-{}", std::process::id()));
+        let mut journal = Journal::new("repeat");
+        journal
+            .append(
+                &dir,
+                Entry::Created {
+                    id: "card1".to_string(),
+                },
+            )
+            .unwrap();
+        // the entries written so far are kept whatever happens next
+        assert_eq!(Journal::load(&dir, &journal.run).unwrap(), journal);
+        journal
+            .append(
+                &dir,
+                Entry::Changed {
+                    id: "card2".to_string(),
+                    id_list: "list1".to_string(),
+                    id_board: "board1".to_string(),
+                    pos: 1024.5,
+                    name: "collapse".to_string(),
+                    desc: "".to_string(),
+                },
+            )
+            .unwrap();
+        journal
+            .append(
+                &dir,
+                Entry::Archived {
+                    id: "card3".to_string(),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(Journal::load(&dir, &journal.run).unwrap(), journal);
+        assert!(Journal::load(&dir, "unknown").is_err());
+
+        // the journal of another run with the same id is not overwritten
+        let mut same = Journal {
+            entries: vec![],
+            ..journal.clone()
+        };
+        assert!(same
+            .append(
+                &dir,
+                Entry::Created {
+                    id: "card4".to_string()
+                }
+            )
+            .is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
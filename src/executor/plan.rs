@@ -0,0 +1,108 @@
+use std::fmt::{self, Display, Formatter};
+
+/// A change of the board the executor would make, collected instead of calling trello in a dry run.
+/// The lists are given by name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Create {
+        card: String,
+        to: String,
+        pos: String,
+    },
+    Copy {
+        card: String,
+        to: String,
+        pos: String,
+        keep: Vec<String>,
+    },
+    Move {
+        card: String,
+        from: String,
+        to: String,
+        pos: String,
+    },
+    Archive {
+        card: String,
+        list: String,
+    },
+    Unarchive {
+        card: String,
+        list: String,
+    },
+    Delete {
+        card: String,
+        list: String,
+    },
+    AddLabel {
+        card: String,
+        label: String,
+    },
+    RemoveLabel {
+        card: String,
+        label: String,
+    },
+    CreateLabel {
+        label: String,
+        color: Option<String>,
+    },
+    Rename {
+        card: String,
+        name: String,
+    },
+    Describe {
+        card: String,
+        desc: String,
+    },
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Create { card, to, pos } => {
+                write!(f, "create '{}' in {} at {}", card, to, pos)
+            }
+            Operation::Copy {
+                card,
+                to,
+                pos,
+                keep,
+            } if keep.is_empty() => write!(f, "copy '{}' to {} at {}", card, to, pos),
+            Operation::Copy {
+                card,
+                to,
+                pos,
+                keep,
+            } => write!(
+                f,
+                "copy '{}' to {} at {} with {}",
+                card,
+                to,
+                pos,
+                keep.join(", ")
+            ),
+            Operation::Move {
+                card,
+                from,
+                to,
+                pos,
+            } => write!(f, "move '{}' from {} to {} at {}", card, from, to, pos),
+            Operation::Archive { card, list } => write!(f, "archive '{}' in {}", card, list),
+            Operation::Unarchive { card, list } => {
+                write!(f, "unarchive '{}' back to {}", card, list)
+            }
+            Operation::Delete { card, list } => write!(f, "delete '{}' from {}", card, list),
+            Operation::AddLabel { card, label } => write!(f, "label '{}' with {}", card, label),
+            Operation::RemoveLabel { card, label } => {
+                write!(f, "remove the label {} from '{}'", label, card)
+            }
+            Operation::CreateLabel { label, color } => match color {
+                Some(color) => write!(f, "create the {} label {}", color, label),
+                None => write!(f, "create the label {}", label),
+            },
+            Operation::Rename { card, name } => write!(f, "rename '{}' to '{}'", card, name),
+            Operation::Describe { card, desc } => {
+                write!(f, "set the description of '{}' to '{}'", card, desc)
+            }
+        }
+    }
+}
@@ -1,24 +1,102 @@
+use std::{
+    cmp::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use super::{error, Executor, State, TaskProcessor};
 use crate::{
     err::FlowError,
     task::tasks::{
-        ActionTask, CardInfo, FilterTask, FlowTask, GroupTask, OrderTask, Place, Source, TakeTask,
-        Target, Task, TaskBody,
+        ActionTask, CardInfo, Check, ConditionTask, ErrorPolicy, Expr, FilterTask, FlowTask,
+        ForEachTask, GroupTask, LabelAction, LabelOp, LabelRef, NewLabel, OrderTask, Place,
+        Predicate, RewriteAction, SortField, SortKey, Source, TakeTask, Target, Task, TaskBody,
     },
-    trello::{Card, List},
+    trello::{Card, Label, List, TrelloApi},
+};
+use rand::{
+    seq::{index, SliceRandom},
+    Rng,
 };
-use rand::{rngs::ThreadRng, seq::SliceRandom, Rng};
-use std::{collections::HashSet, vec};
 
-fn find_list(executor: &mut Executor, name: &str) -> Result<List, FlowError> {
+fn find_list<T: TrelloApi>(executor: &mut Executor<T>, name: &str) -> Result<List, FlowError> {
     executor
-        .connector
-        .list_by_name(&executor.board_id, &name)
-        .ok_or(error(format!("the column {} is not found", name)))
+        .list_by_name(name)?
+        .ok_or_else(|| error(format!("the column {} is not found", name)))
+}
+
+/// The column of the target on its board or on the board of the task.
+fn target_list<T: TrelloApi>(
+    executor: &mut Executor<T>,
+    board: &Option<String>,
+    name: &str,
+) -> Result<List, FlowError> {
+    match board {
+        Some(board) => {
+            let board_id = executor.board_by_name(board)?;
+            executor
+                .list_on_board(&board_id, name)?
+                .ok_or_else(|| error(format!("the column {} is not found on {}", name, board)))
+        }
+        None => find_list(executor, name),
+    }
+}
+
+/// Turns a place into the `pos` trello expects.
+/// The random place is a position between two neighbouring cards of the list
+/// (or before the first or after the last one), the moved card itself is not counted.
+fn position<T: TrelloApi>(
+    executor: &mut Executor<T>,
+    place: &Place,
+    list_id: &str,
+    card_id: Option<&str>,
+) -> Result<String, FlowError> {
+    match place {
+        Place::Top => Ok("top".to_string()),
+        Place::Bottom => Ok("bottom".to_string()),
+        Place::Random => {
+            let positions: Vec<f32> = executor
+                .cards_in_list(list_id)?
+                .into_iter()
+                .filter(|c| Some(c.id.as_str()) != card_id)
+                .map(|c| c.pos)
+                .collect();
+            let gap = executor.rand.gen_range(0..=positions.len());
+            let pos = match (gap.checked_sub(1).map(|i| positions[i]), positions.get(gap)) {
+                (None, None) => 65536.0,
+                (None, Some(next)) => next / 2.0,
+                (Some(prev), None) => prev + 65536.0,
+                (Some(prev), Some(next)) => (prev + next) / 2.0,
+            };
+            Ok(pos.to_string())
+        }
+    }
+}
+
+impl TaskProcessor for Task {
+    /// Runs the body on the board of the task, the board of the caller is restored afterwards.
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
+        let Some(board) = &self.board else {
+            return self.body.process(executor, state);
+        };
+        let board_id = executor.board_by_name(board)?;
+        info!("run {} on the board {}", self.name, board);
+        let caller = std::mem::replace(&mut executor.board_id, board_id);
+        let res = self.body.process(executor, state);
+        executor.board_id = caller;
+        res
+    }
 }
 
 impl TaskProcessor for TaskBody {
-    fn process(&self, executor: &mut Executor, state: State) -> Result<State, FlowError> {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
         match self {
             TaskBody::Take(t) => t.process(executor, state),
             TaskBody::Order(t) => t.process(executor, state),
@@ -26,18 +104,39 @@ impl TaskProcessor for TaskBody {
             TaskBody::Action(t) => t.process(executor, state),
             TaskBody::Group(t) => t.process(executor, state),
             TaskBody::Flow(t) => t.process(executor, state),
+            TaskBody::Condition(t) => t.process(executor, state),
+            TaskBody::ForEach(t) => t.process(executor, state),
         }
     }
 }
 impl TaskProcessor for Source {
-    fn process(&self, executor: &mut Executor, state: State) -> Result<State, FlowError> {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
         info!("process source {:?}", self);
         let items = match &self {
             Source::Pipe => state.cards()?,
-            Source::Board => executor.connector.cards(&executor.board_id),
+            Source::Board(board) => {
+                let board_id = executor.board_by_name(board)?;
+                executor.board_cards(&board_id)?
+            }
             Source::Column(name) => {
                 let list = find_list(executor, name)?;
-                executor.connector.cards_in_list(&list.id)
+                executor.cards_in_list(&list.id)?
+            }
+            Source::ArchivedBoard(board) => {
+                let board_id = executor.board_by_name(board)?;
+                executor.archived_cards(&board_id)?
+            }
+            Source::ArchivedColumn(name) => {
+                let list = find_list(executor, name)?;
+                let board_id = executor.board_id.clone();
+                let mut cards = executor.archived_cards(&board_id)?;
+                cards.retain(|c| c.id_list == list.id);
+                cards.sort_by(|l, r| l.pos.total_cmp(&r.pos));
+                cards
             }
         };
         info!("taken {:?} cards", items.len());
@@ -46,7 +145,11 @@ impl TaskProcessor for Source {
 }
 
 impl TaskProcessor for ActionTask {
-    fn process(&self, executor: &mut Executor, state: State) -> Result<State, FlowError> {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
         match self {
             ActionTask::PrintToConsole => {
                 info!("print...");
@@ -56,117 +159,345 @@ impl TaskProcessor for ActionTask {
                 }
                 Ok(state)
             }
-            ActionTask::CopyToColumn(Target { column, place }) => {
-                let lid = find_list(executor, &column)?.id;
+            ActionTask::Refresh => {
+                info!("refresh the board");
+                executor.refresh();
+                Ok(state)
+            }
+            ActionTask::CopyToColumn(
+                Target {
+                    column,
+                    place,
+                    board,
+                },
+                keep,
+            ) => {
+                let lid = target_list(executor, board, column)?.id;
                 let cards: Vec<Card> = state.cards()?;
-                info!("copy to {}", column);
-                match place {
-                    Place::Top => cards.into_iter().for_each(|c| {
-                        executor.connector.create_card(&lid, &c.name, "top");
-                    }),
-
-                    Place::Bottom => cards.into_iter().for_each(|c| {
-                        executor.connector.create_card(&lid, &c.name, "bottom");
-                    }),
-                    Place::Random => todo!(),
+                info!("copy to {} with {:?}", column, keep);
+                let mut copies = vec![];
+                for c in cards {
+                    let pos = position(executor, place, &lid, None)?;
+                    copies.push(executor.copy_card(&c, &lid, &pos, keep)?);
                 }
-                Ok(State::End)
+                Ok(State::Pipe(copies))
             }
-            ActionTask::MoveToColumn(Target { column, place }) => {
-                let lid = find_list(executor, &column)?;
+            ActionTask::MoveToColumn(Target {
+                column,
+                place,
+                board,
+            }) => {
+                let lid = target_list(executor, board, column)?.id;
                 let cards = state.cards()?;
                 info!("move to {}", column);
-                match place {
-                    Place::Top => cards.into_iter().for_each(|c| {
-                        executor.connector.mov_card(&c.id, &lid.id, "top");
-                    }),
-
-                    Place::Bottom => cards.into_iter().for_each(|c| {
-                        executor.connector.mov_card(&c.id, &lid.id, "bottom");
-                    }),
-                    Place::Random => todo!(),
+                let mut moved = vec![];
+                for c in cards {
+                    let pos = position(executor, place, &lid, Some(&c.id))?;
+                    moved.push(executor.mov_card(&c, &lid, &pos)?);
                 }
-                Ok(State::End)
+                Ok(State::Pipe(moved))
             }
-            ActionTask::AddToColumn(CardInfo { name }, Target { column, place }) => {
-                let lid = find_list(executor, &column)?;
+            ActionTask::AddToColumn(
+                CardInfo { name },
+                Target {
+                    column,
+                    place,
+                    board,
+                },
+            ) => {
+                let lid = target_list(executor, board, column)?.id;
                 info!("add a card to {}", column);
-                match place {
-                    Place::Top => executor.connector.create_card(&lid.id, name, "top"),
-                    Place::Bottom => executor.connector.create_card(&lid.id, name, "bottom"),
-                    Place::Random => todo!(),
-                };
+                let pos = position(executor, place, &lid, None)?;
+                let card = executor.create_card(&lid, name, &pos)?;
+                Ok(State::Pipe(vec![card]))
+            }
+            ActionTask::ApplyOrder => {
+                let mut cards = state.cards()?;
+                // the indices of the cards in the pipe by their lists
+                let mut lists: Vec<(String, Vec<usize>)> = vec![];
+                for (i, c) in cards.iter().enumerate() {
+                    match lists.iter_mut().find(|(id, _)| *id == c.id_list) {
+                        Some((_, idx)) => idx.push(i),
+                        None => lists.push((c.id_list.clone(), vec![i])),
+                    }
+                }
+                for (lid, idx) in lists {
+                    let positions: Vec<f32> = idx.iter().map(|&i| cards[i].pos).collect();
+                    let moves = reposition(&positions);
+                    info!(
+                        "apply the order of {} cards by {} moves",
+                        idx.len(),
+                        moves.len()
+                    );
+                    for (i, pos) in moves {
+                        cards[idx[i]] =
+                            executor.mov_card(&cards[idx[i]], &lid, &pos.to_string())?;
+                    }
+                }
+                Ok(State::Pipe(cards))
+            }
+            ActionTask::Archive => {
+                let cards = state.cards()?;
+                info!("archive {} cards", cards.len());
+                let mut archived = vec![];
+                for c in cards {
+                    archived.push(executor.archive_card(&c)?);
+                }
+                Ok(State::Pipe(archived))
+            }
+            ActionTask::Unarchive => {
+                let cards = state.cards()?;
+                info!("unarchive {} cards", cards.len());
+                let mut unarchived = vec![];
+                for c in cards {
+                    unarchived.push(executor.unarchive_card(&c)?);
+                }
+                Ok(State::Pipe(unarchived))
+            }
+            ActionTask::Delete => {
+                let cards = state.cards()?;
+                info!("delete {} cards", cards.len());
+                for c in cards {
+                    executor.delete_card(&c)?;
+                }
+                // the deleted cards cannot go on
                 Ok(State::End)
             }
+            ActionTask::Labels(LabelAction { op, labels, create }) => {
+                let mut wanted = vec![];
+                for NewLabel { name, color } in labels {
+                    let label = match executor.label_by_name(name, false)? {
+                        Some(label) => label,
+                        None if *create => executor.create_label(name, color.as_deref())?,
+                        None => return Err(error(format!("the label {} is not found", name))),
+                    };
+                    wanted.push(label);
+                }
+                let all = executor.labels()?;
+                let cards = state.cards()?;
+                info!("{:?} the labels of {} cards", op, cards.len());
+                let mut labeled = vec![];
+                for c in cards {
+                    let mut card = c;
+                    match op {
+                        LabelOp::Add => {
+                            for l in wanted.iter() {
+                                card = executor.add_label(&card, l)?;
+                            }
+                        }
+                        LabelOp::Remove => {
+                            for l in wanted.iter() {
+                                card = executor.remove_label(&card, l)?;
+                            }
+                        }
+                        LabelOp::Toggle => {
+                            for l in wanted.iter() {
+                                card = if card.id_labels.contains(&l.id) {
+                                    executor.remove_label(&card, l)?
+                                } else {
+                                    executor.add_label(&card, l)?
+                                };
+                            }
+                        }
+                        LabelOp::Replace => {
+                            let extra: Vec<&Label> = all
+                                .iter()
+                                .filter(|l| card.id_labels.contains(&l.id))
+                                .filter(|l| !wanted.iter().any(|w| w.id == l.id))
+                                .collect();
+                            for l in extra {
+                                card = executor.remove_label(&card, l)?;
+                            }
+                            for l in wanted.iter() {
+                                card = executor.add_label(&card, l)?;
+                            }
+                        }
+                    }
+                    labeled.push(card);
+                }
+                Ok(State::Pipe(labeled))
+            }
+            ActionTask::Rewrite(RewriteAction { name, desc }) => {
+                let labels = executor.labels()?;
+                let date = today();
+                let cards = state.cards()?;
+                info!("rewrite {} cards", cards.len());
+                let mut rewritten = vec![];
+                for c in cards {
+                    let list = executor.list_name(&c.id_list)?;
+                    let value = |field: &str| match field {
+                        "name" => c.name.clone(),
+                        "desc" => c.desc.clone(),
+                        "list" => list.clone(),
+                        "url" => c.short_url.clone(),
+                        "labels" => labels
+                            .iter()
+                            .filter(|l| c.id_labels.contains(&l.id))
+                            .map(|l| l.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        "due" => c.due.clone().unwrap_or_default(),
+                        "date" => date.clone(),
+                        _ => String::new(),
+                    };
+                    let new_name = name.as_ref().map(|r| r.apply(&c.name, value));
+                    let new_desc = desc.as_ref().map(|r| r.apply(&c.desc, value));
+                    rewritten.push(executor.rewrite_card(
+                        &c,
+                        new_name.as_deref().unwrap_or(&c.name),
+                        new_desc.as_deref().unwrap_or(&c.desc),
+                    )?);
+                }
+                Ok(State::Pipe(rewritten))
+            }
+        }
+    }
+}
+
+/// Today as `YYYY-MM-DD` in UTC.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or_default();
+    let (y, m, d) = civil_date(days as i64);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// The year, the month and the day of the days since 1970-01-01 in the gregorian calendar.
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    // the years start in March so the leap day is the last one
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
+
+/// The new positions making `positions` increasing, as `(index, position)`.
+/// The longest increasing subsequence stays in place and the other cards are put
+/// between their kept neighbours, all the cards are renumbered
+/// only if the gaps are too small for `f32`.
+fn reposition(positions: &[f32]) -> Vec<(usize, f32)> {
+    // the length of the longest increasing subsequence ending at i and the previous index
+    let mut longest: Vec<(usize, Option<usize>)> = Vec::with_capacity(positions.len());
+    for (i, pos) in positions.iter().enumerate() {
+        let best = (0..i)
+            .filter(|&j| positions[j] < *pos)
+            .max_by_key(|&j| longest[j].0);
+        longest.push(match best {
+            Some(j) => (longest[j].0 + 1, Some(j)),
+            None => (1, None),
+        });
+    }
+    let mut kept = vec![false; positions.len()];
+    let mut last = (0..positions.len()).max_by_key(|&i| longest[i].0);
+    while let Some(i) = last {
+        kept[i] = true;
+        last = longest[i].1;
+    }
+
+    let mut moves = vec![];
+    let mut prev: Option<f32> = None;
+    for i in 0..positions.len() {
+        if kept[i] {
+            prev = Some(positions[i]);
+            continue;
+        }
+        let next = (i + 1..positions.len())
+            .find(|&j| kept[j])
+            .map(|j| positions[j]);
+        let pos = match (prev, next) {
+            (None, None) => 65536.0,
+            (None, Some(next)) => next / 2.0,
+            (Some(prev), None) => prev + 65536.0,
+            (Some(prev), Some(next)) => (prev + next) / 2.0,
+        };
+        if prev.map_or(pos <= 0.0, |p| pos <= p) || next.is_some_and(|n| pos >= n) {
+            return (0..positions.len())
+                .map(|i| (i, (i + 1) as f32 * 65536.0))
+                .filter(|(i, pos)| positions[*i] != *pos)
+                .collect();
         }
+        moves.push((i, pos));
+        prev = Some(pos);
     }
+    moves
 }
 
 impl TaskProcessor for GroupTask {
-    fn process(&self, executor: &mut Executor, state: State) -> Result<State, FlowError> {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        _state: State,
+    ) -> Result<State, FlowError> {
         info!("execute a group task:");
         for step in &self.steps {
             info!("execute a step: {}", step);
-            let _ = executor.start(step.clone())?;
+            let _ = executor.run(step)?;
         }
         Ok(State::End)
     }
 }
 
 impl TaskProcessor for TakeTask {
-    fn process(&self, executor: &mut Executor, state: State) -> Result<State, FlowError> {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
         let entities = self.src.process(executor, state)?.cards()?;
         let max = entities.len();
         info!(
-            "taken cards from {:?} and then will cut by {}",
-            self.src, max
+            "taken {} cards from {:?} and then will skip {} and cut by {}",
+            max, self.src, self.skip, self.size
         );
-        match self.size {
-            i if i > 0 && i < max => match self.place {
-                Place::Top => Ok(State::Pipe(entities[..i].to_vec())),
-                Place::Bottom => Ok(State::Pipe(entities[i..].to_vec())),
-                Place::Random => {
-                    let mut ids = HashSet::new();
-
-                    while ids.len() != i {
-                        let rand_idx = executor.rand.gen_range(0..max);
-                        ids.insert(rand_idx);
-                    }
-                    let mut res = vec![];
-                    for i in ids {
-                        res.push(entities[i].clone())
-                    }
-                    Ok(State::Pipe(res))
-                }
-            },
-            _ => Ok(State::Pipe(entities)),
+        // the skipped cards are counted from the bottom for the bottom place
+        // and from the top otherwise
+        let skip = self.skip.min(max);
+        let rest = max - skip;
+        let size = match self.size {
+            i if i > 0 && i < rest => i,
+            _ => rest,
+        };
+        match self.place {
+            Place::Top => Ok(State::Pipe(entities[skip..skip + size].to_vec())),
+            Place::Bottom => Ok(State::Pipe(entities[rest - size..rest].to_vec())),
+            Place::Random if size == rest => Ok(State::Pipe(entities[skip..].to_vec())),
+            Place::Random => Ok(State::Pipe(
+                index::sample(&mut executor.rand, rest, size)
+                    .into_iter()
+                    .map(|i| entities[skip + i].clone())
+                    .collect(),
+            )),
         }
     }
 }
 
 impl TaskProcessor for FilterTask {
-    fn process(&self, executor: &mut Executor, state: State) -> Result<State, FlowError> {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
         let cards = state.cards()?;
         info!("filter by {:?}", self);
         match self {
-            FilterTask::Name(name, case) => Ok(State::Pipe(
-                cards
-                    .into_iter()
-                    .filter(|c| {
-                        if *case {
-                            c.name == *name
-                        } else {
-                            c.name.to_lowercase() == name.to_lowercase()
-                        }
-                    })
-                    .collect(),
+            FilterTask::Name(text) => Ok(State::Pipe(
+                cards.into_iter().filter(|c| text.test(&c.name)).collect(),
+            )),
+            FilterTask::Desc(text) => Ok(State::Pipe(
+                cards.into_iter().filter(|c| text.test(&c.desc)).collect(),
             )),
             FilterTask::Label(label, case) => {
                 let label = executor
-                    .connector
-                    .label_by_name(&executor.board_id, label, *case)
+                    .label_by_name(label, *case)?
                     .map(|l| l.id)
-                    .ok_or(error(format!("the label is not found")))?;
+                    .ok_or_else(|| error("the label is not found".to_string()))?;
                 Ok(State::Pipe(
                     cards
                         .into_iter()
@@ -174,23 +505,143 @@ impl TaskProcessor for FilterTask {
                         .collect(),
                 ))
             }
+            FilterTask::Labels(filter) => {
+                let ids = |executor: &mut Executor<T>, refs: &[LabelRef]| {
+                    refs.iter()
+                        .map(|r| label_ids(executor, r, filter.case))
+                        .collect::<Result<Vec<_>, _>>()
+                };
+                let any_of = ids(executor, &filter.any_of)?;
+                let all_of = ids(executor, &filter.all_of)?;
+                let none_of = ids(executor, &filter.none_of)?;
+                let has =
+                    |c: &Card, ids: &Vec<String>| ids.iter().any(|id| c.id_labels.contains(id));
+                Ok(State::Pipe(
+                    cards
+                        .into_iter()
+                        .filter(|c| any_of.is_empty() || any_of.iter().any(|ids| has(c, ids)))
+                        .filter(|c| all_of.iter().all(|ids| has(c, ids)))
+                        .filter(|c| !none_of.iter().any(|ids| has(c, ids)))
+                        .collect(),
+                ))
+            }
+            FilterTask::NoLabels => Ok(State::Pipe(
+                cards
+                    .into_iter()
+                    .filter(|c| c.id_labels.is_empty())
+                    .collect(),
+            )),
+            FilterTask::Expr(expr) => {
+                let mut res = vec![];
+                for c in cards {
+                    if test(executor, expr, &c)? {
+                        res.push(c);
+                    }
+                }
+                Ok(State::Pipe(res))
+            }
+        }
+    }
+}
+
+/// The ids of the labels with the name or the colour, `ignore_case` compares the names like `find_label`,
+/// the unknown names are errors and the unknown colours give no labels.
+fn label_ids<T: TrelloApi>(
+    executor: &mut Executor<T>,
+    label: &LabelRef,
+    ignore_case: bool,
+) -> Result<Vec<String>, FlowError> {
+    let labels = executor.labels()?;
+    let ids: Vec<String> = labels
+        .into_iter()
+        .filter(|l| match label {
+            LabelRef::Name(name) if ignore_case => l.name.to_lowercase() == name.to_lowercase(),
+            LabelRef::Name(name) => &l.name == name,
+            LabelRef::Color(color) => l.color.as_ref() == Some(color),
+        })
+        .map(|l| l.id)
+        .collect();
+    match label {
+        LabelRef::Name(name) if ids.is_empty() => {
+            Err(error(format!("the label {} is not found", name)))
         }
+        _ => Ok(ids),
     }
 }
 
+/// Checks the card against the expression, the unknown labels and columns are errors.
+fn test<T: TrelloApi>(
+    executor: &mut Executor<T>,
+    expr: &Expr,
+    card: &Card,
+) -> Result<bool, FlowError> {
+    Ok(match expr {
+        Expr::And(exprs) => {
+            for e in exprs {
+                if !test(executor, e, card)? {
+                    return Ok(false);
+                }
+            }
+            true
+        }
+        Expr::Or(exprs) => {
+            for e in exprs {
+                if test(executor, e, card)? {
+                    return Ok(true);
+                }
+            }
+            false
+        }
+        Expr::Not(e) => !test(executor, e, card)?,
+        Expr::Name(text) => text.test(&card.name),
+        Expr::Desc(text) => text.test(&card.desc),
+        Expr::Label(label, case) => {
+            let label = executor
+                .label_by_name(label, !*case)?
+                .ok_or_else(|| error(format!("the label {} is not found", label)))?;
+            card.id_labels.contains(&label.id)
+        }
+        Expr::Color(color) => {
+            let ids = label_ids(executor, &LabelRef::Color(color.clone()), false)?;
+            ids.iter().any(|id| card.id_labels.contains(id))
+        }
+        Expr::NoLabels => card.id_labels.is_empty(),
+        Expr::List(name) => find_list(executor, name)?.id == card.id_list,
+        Expr::Pos(op, value) => executor
+            .cards_in_list(&card.id_list)?
+            .iter()
+            .position(|c| c.id == card.id)
+            .map(|idx| op.test(idx, *value))
+            .unwrap_or(false),
+    })
+}
+
 impl TaskProcessor for OrderTask {
-    fn process(&self, executor: &mut Executor, state: State) -> Result<State, FlowError> {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
         let mut items = self.source()?.process(executor, state)?.cards()?;
         info!("order items by {:?} ", self);
-        let mut items = match self {
+        let items = match self {
             OrderTask::Shuffle(_s) => {
                 items.shuffle(&mut executor.rand);
                 items
             }
-            OrderTask::Sort(_s) => {
+            OrderTask::Sort(_s, keys) if keys.is_empty() => {
                 items.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
                 items
             }
+            OrderTask::Sort(_s, keys) => {
+                items.sort_by(|lhs, rhs| {
+                    keys.iter()
+                        .map(|k| compare(k, lhs, rhs))
+                        .find(|o| o.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                });
+                items
+            }
             OrderTask::Reverse(_s) => {
                 items.reverse();
                 items
@@ -200,57 +651,364 @@ impl TaskProcessor for OrderTask {
     }
 }
 
+/// Compares the cards by the key, the cards missing the value go last in both directions.
+fn compare(key: &SortKey, lhs: &Card, rhs: &Card) -> Ordering {
+    fn present<V: Ord>(lhs: Option<V>, rhs: Option<V>) -> Result<Ordering, Ordering> {
+        match (lhs, rhs) {
+            (Some(l), Some(r)) => Ok(l.cmp(&r)),
+            (Some(_), None) => Err(Ordering::Less),
+            (None, Some(_)) => Err(Ordering::Greater),
+            (None, None) => Err(Ordering::Equal),
+        }
+    }
+    let ord = match key.field {
+        SortField::Pos => Ok(lhs.pos.total_cmp(&rhs.pos)),
+        SortField::Name => Ok(lhs.name.cmp(&rhs.name)),
+        SortField::DescLength => Ok(lhs.desc.chars().count().cmp(&rhs.desc.chars().count())),
+        SortField::Labels => Ok(lhs.id_labels.len().cmp(&rhs.id_labels.len())),
+        // the dates are in ISO 8601 and go in the order of the strings
+        SortField::Activity => present(
+            lhs.date_last_activity.as_ref(),
+            rhs.date_last_activity.as_ref(),
+        ),
+        SortField::Due => present(lhs.due.as_ref(), rhs.due.as_ref()),
+        SortField::Created => present(lhs.created(), rhs.created()),
+    };
+    match ord {
+        Ok(ord) if key.desc => ord.reverse(),
+        Ok(ord) | Err(ord) => ord,
+    }
+}
+
 impl TaskProcessor for FlowTask {
-    fn process(&self, executor: &mut Executor, state: State) -> Result<State, FlowError> {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
         info!("flow task:");
 
-        self.steps.iter().fold(Ok(state), |st, step| {
+        self.steps.iter().try_fold(state, |st, step| {
             info!("execute step: {}", step);
-            executor.ctx.task(step)?.body.process(executor, st?)
+            executor.ctx.task(step)?.process(executor, st)
         })
     }
 }
 
+/// The cards of the pipe the filter of the predicate keeps.
+fn kept<T: TrelloApi>(
+    executor: &mut Executor<T>,
+    check: &Check,
+    state: &State,
+) -> Result<Vec<Card>, FlowError> {
+    match check {
+        Check::Filter(task) => executor
+            .ctx
+            .task(task)?
+            .process(executor, state.clone())?
+            .cards(),
+        Check::Expr(expr) => {
+            let mut res = vec![];
+            for c in state.cards()? {
+                if test(executor, expr, &c)? {
+                    res.push(c);
+                }
+            }
+            Ok(res)
+        }
+    }
+}
+
+impl TaskProcessor for ConditionTask {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
+        let size = state.cards()?.len();
+        let holds = match &self.predicate {
+            Predicate::Empty => size == 0,
+            Predicate::Size(op, value) => op.test(size, *value),
+            Predicate::Any(check) => !kept(executor, check, &state)?.is_empty(),
+            Predicate::All(check) => kept(executor, check, &state)?.len() == size,
+        };
+        info!("the condition {:?} is {}", self.predicate, holds);
+
+        match (holds, &self.otherwise) {
+            (true, _) => executor.ctx.task(&self.then)?.process(executor, state),
+            (false, Some(otherwise)) => executor.ctx.task(otherwise)?.process(executor, state),
+            (false, None) => Ok(state),
+        }
+    }
+}
+
+impl TaskProcessor for ForEachTask {
+    fn process<T: TrelloApi>(
+        &self,
+        executor: &mut Executor<T>,
+        state: State,
+    ) -> Result<State, FlowError> {
+        let task = executor.ctx.task(&self.task)?;
+        let mut res = vec![];
+        for card in state.cards()? {
+            info!("run {} for the card {}", self.task, card.name);
+            match task.process(executor, State::Pipe(vec![card.clone()])) {
+                Ok(State::Pipe(cards)) => res.extend(cards),
+                Ok(_) => {}
+                Err(e) if self.on_error == ErrorPolicy::Continue => {
+                    warn!("{} failed for the card {}: {}", self.task, card.name, e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(State::Pipe(res))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{collections::HashMap, fs};
 
+    use super::{civil_date, find_list, reposition};
     use crate::{
-        executor::{Executor, State, TaskProcessor},
+        executor::{
+            tests::{eng, journal_dir},
+            Executor, State, TaskProcessor,
+        },
         task::{
             context::from_str,
-            tasks::{TakeTask, TaskBody},
+            tasks::{Place, Source, TakeTask},
         },
-        trello::TrelloConnector,
+        trello::{fake::FakeBoard, Card, TrelloApi},
     };
 
-    fn trello() -> TrelloConnector {
-        TrelloConnector::from_file("/home/besok/projects/trello-flow/examples/trello_cred.yml")
+    fn executor(tasks: &str) -> Executor<FakeBoard> {
+        let ctx = from_str(tasks, HashMap::new()).unwrap();
+        Executor::new(ctx, eng()).unwrap()
+    }
+
+    fn names(state: &State) -> Vec<String> {
+        state.cards().unwrap().into_iter().map(|c| c.name).collect()
+    }
+
+    fn fixed_cards(n: usize) -> State {
+        State::Pipe(
+            (0..n)
+                .map(|i| Card {
+                    id: format!("card{}", i),
+                    pos: i as f32,
+                    name: format!("{}", i),
+                    desc: String::new(),
+                    id_list: "list".to_string(),
+                    id_board: String::new(),
+                    id_labels: vec![],
+                    url: String::new(),
+                    short_url: String::new(),
+                    closed: false,
+                    date_last_activity: None,
+                    due: None,
+                })
+                .collect(),
+        )
+    }
+
+    fn take_fixed(size: usize, skip: usize, place: Place) -> Vec<String> {
+        let mut e = executor("board: ENG");
+        let task = TakeTask {
+            src: Source::Pipe,
+            size,
+            skip,
+            place,
+        };
+        names(&task.process(&mut e, fixed_cards(10)).unwrap())
+    }
+
+    #[test]
+    fn take_places() {
+        assert_eq!(take_fixed(3, 0, Place::Top), ["0", "1", "2"]);
+        assert_eq!(take_fixed(3, 0, Place::Bottom), ["7", "8", "9"]);
+        assert_eq!(take_fixed(0, 0, Place::Bottom).len(), 10);
+        assert_eq!(take_fixed(20, 0, Place::Top).len(), 10);
+        assert_eq!(take_fixed(3, 0, Place::Random).len(), 3);
+    }
+
+    #[test]
+    fn take_skip() {
+        assert_eq!(take_fixed(3, 2, Place::Top), ["2", "3", "4"]);
+        assert_eq!(take_fixed(3, 2, Place::Bottom), ["5", "6", "7"]);
+        assert_eq!(take_fixed(0, 7, Place::Top), ["7", "8", "9"]);
+        assert_eq!(take_fixed(5, 7, Place::Bottom), ["0", "1", "2"]);
+        assert!(take_fixed(3, 10, Place::Top).is_empty());
+        assert!(take_fixed(3, 20, Place::Bottom).is_empty());
+
+        let random = take_fixed(3, 5, Place::Random);
+        assert_eq!(random.len(), 3);
+        assert!(random.iter().all(|n| n.parse::<usize>().unwrap() >= 5));
+    }
+
+    #[test]
+    fn filter() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_from_archive:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+            filter_demand:
+                type: filter
+                params:
+                    by: label
+                    rhs: demand
+            flow:
+                type: flow
+                params:
+                    - take_from_archive
+                    - filter_demand
+        "#,
+        );
+        let task = e.ctx.task("flow").unwrap();
+
+        let res = task.body.process(&mut e, State::Init).unwrap();
+        assert_eq!(res.cards().unwrap().len(), 10);
+        assert_eq!(names(&res)[..2], ["word 0", "word 3"]);
+    }
+    #[test]
+    fn filter_expr() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_from_archive:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+            filter:
+                type: filter
+                params:
+                    expr:
+                        or:
+                            - and:
+                                - label: demand
+                                - not:
+                                    name: WORD 0
+                            - pos:
+                                op: lt
+                                value: 2
+            in_later:
+                type: filter
+                params:
+                    expr:
+                        list: Later
+            unknown_label:
+                type: filter
+                params:
+                    expr:
+                        label: demand
+                        case: true
+        "#,
+        );
+        let archive = e
+            .ctx
+            .task("take_from_archive")
+            .unwrap()
+            .body
+            .process(&mut e, State::Init)
+            .unwrap();
+        let run = |e: &mut Executor<FakeBoard>, task: &str| {
+            let task = e.ctx.task(task).unwrap();
+            task.body.process(e, archive.clone())
+        };
+
+        let res = run(&mut e, "filter").unwrap();
+        assert_eq!(names(&res).len(), 11);
+        assert_eq!(names(&res)[..4], ["word 0", "word 1", "word 3", "word 6"]);
+        assert!(names(&run(&mut e, "in_later").unwrap()).is_empty());
+        assert!(run(&mut e, "unknown_label").is_err());
+    }
+
+    #[test]
+    fn filter_labels() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_from_archive:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+            any_of:
+                type: filter
+                params:
+                    by: labels
+                    any_of:
+                        - Demand
+                        - color: red
+            all_none:
+                type: filter
+                params:
+                    by: labels
+                    all_of:
+                        - Demand
+                    none_of:
+                        - color: green
+            unlabeled:
+                type: filter
+                params:
+                    by: no_labels
+            red:
+                type: filter
+                params:
+                    expr:
+                        color: red
+            lowercase:
+                type: filter
+                params:
+                    by: labels
+                    any_of:
+                        - demand
+            unknown:
+                type: filter
+                params:
+                    by: labels
+                    none_of:
+                        - demand
+                    case: false
+        "#,
+        );
+        let archive = e
+            .ctx
+            .task("take_from_archive")
             .unwrap()
+            .body
+            .process(&mut e, State::Init)
+            .unwrap();
+        let run = |e: &mut Executor<FakeBoard>, task: &str| {
+            let task = e.ctx.task(task).unwrap();
+            task.body.process(e, archive.clone())
+        };
+
+        assert_eq!(names(&run(&mut e, "any_of").unwrap()).len(), 20);
+        assert!(names(&run(&mut e, "all_none").unwrap()).is_empty());
+        let unlabeled = names(&run(&mut e, "unlabeled").unwrap());
+        assert_eq!(unlabeled.len(), 10);
+        assert_eq!(unlabeled[..2], ["word 2", "word 5"]);
+        assert_eq!(
+            names(&run(&mut e, "red").unwrap())[..2],
+            ["word 1", "word 4"]
+        );
+        // the names are compared ignoring the case unless case is false, like by label
+        assert_eq!(names(&run(&mut e, "lowercase").unwrap()).len(), 10);
+        assert!(run(&mut e, "unknown").is_err());
     }
-    // #[test]
-    // fn filter() {
-    //     let ctx = from_str(
-    //         r#"
-    //         board: ENG
-    //         filter_demand:
-    //             type: filter
-    //             params:
-    //                 by: label
-    //                 rhs: demand
-    //     "#,
-    //     )
-    //     .unwrap();
-
-    //     let mut e = Executor::new(ctx.clone(), trello()).unwrap();
-    //     let task = ctx.tasks.get("filter_demand").unwrap().clone();
-
-    //     let res = task.body.process(&mut e).unwrap();
-    //     println!("{:?}", res.cards().unwrap());
-    // }
+
     #[test]
     fn take() {
-        let ctx = from_str(
+        let mut e = executor(
             r#"
             board: ENG
             take_from_archive:
@@ -262,19 +1020,15 @@ mod tests {
                     size: 10
                     place: random    
         "#,
-            HashMap::new(),
-        )
-        .unwrap();
-
-        let mut e = Executor::new(ctx.clone(), trello(), HashMap::new()).unwrap();
-        let task = ctx.tasks.get("take_from_archive").unwrap().clone();
+        );
+        let task = e.ctx.task("take_from_archive").unwrap();
 
         let res = task.body.process(&mut e, State::Init).unwrap();
-        println!("{:?}", res.cards().unwrap());
+        assert_eq!(res.cards().unwrap().len(), 10);
     }
     #[test]
     fn sort() {
-        let ctx = from_str(
+        let mut e = executor(
             r#"
             board: ENG
             shuffle_idioms:
@@ -285,20 +1039,59 @@ mod tests {
                         type: column # pipe by default and all from can be omitted
                         source: Idioms  
         "#,
-            HashMap::new(),
-        )
-        .unwrap();
-
-        let mut e = Executor::new(ctx.clone(), trello(), HashMap::new()).unwrap();
-        let task = ctx.tasks.get("shuffle_idioms").unwrap().clone();
+        );
+        let task = e.ctx.task("shuffle_idioms").unwrap();
 
         let res = task.body.process(&mut e, State::Init).unwrap();
-        println!("{:?}", res.cards().unwrap());
+        assert_eq!(
+            names(&res),
+            vec!["idiom 0", "idiom 1", "idiom 2", "idiom 3", "idiom 4"]
+        );
+    }
+
+    #[test]
+    fn sort_keys() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            by_due:
+                type: order
+                params:
+                    type: sort
+                    by:
+                        - due
+                        - labels desc
+                        - created
+            by_due_desc:
+                type: order
+                params:
+                    type: sort
+                    by:
+                        - due desc
+                        - created
+        "#,
+        );
+        let mut cards = fixed_cards(4).cards().unwrap();
+        cards[0].due = Some("2023-03-01T10:00:00.000Z".to_string());
+        cards[0].id_labels = vec!["a".to_string()];
+        cards[1].id = "64000000aaaaaaaaaaaaaaaa".to_string();
+        cards[1].id_labels = vec!["a".to_string(), "b".to_string()];
+        cards[2].due = Some("2023-01-01T10:00:00.000Z".to_string());
+        cards[3].id = "63000000bbbbbbbbbbbbbbbb".to_string();
+        cards[3].id_labels = vec!["a".to_string(), "b".to_string()];
+
+        let by_due = e.ctx.task("by_due").unwrap();
+        let res = by_due.body.process(&mut e, State::Pipe(cards.clone()));
+        assert_eq!(names(&res.unwrap()), vec!["2", "0", "3", "1"]);
+
+        let by_due_desc = e.ctx.task("by_due_desc").unwrap();
+        let res = by_due_desc.body.process(&mut e, State::Pipe(cards));
+        assert_eq!(names(&res.unwrap()), vec!["0", "2", "3", "1"]);
     }
 
     #[test]
     fn flow() {
-        let ctx = from_str(
+        let mut e = executor(
             r#"
             board: ENG
             take_from_archive:
@@ -320,30 +1113,196 @@ mod tests {
                     - take_from_archive
                     - shuffle        
         "#,
-            HashMap::new(),
-        )
-        .unwrap();
-
-        let mut e = Executor::new(ctx.clone(), trello(), HashMap::new()).unwrap();
-        let task = ctx.tasks.get("flow_task").unwrap().clone();
+        );
+        let task = e.ctx.task("flow_task").unwrap();
 
         let res = task.body.process(&mut e, State::Init).unwrap();
-        let card_names: Vec<String> = res
-            .cards()
-            .unwrap()
-            .iter()
-            .map(|c| c.name.clone())
-            .collect();
+        let card_names = names(&res);
+        let mut sorted = card_names.clone();
+        sorted.sort();
 
-        println!("{:?}", card_names);
+        assert_eq!(card_names.len(), 10);
+        assert_eq!(card_names, sorted);
     }
 
-    #[test]
-    fn action_move() {
-        let ctx = from_str(
-            r#"
+    const CONDITION: &str = r#"
             board: ENG
-            take_from_archive:
+            take_idioms:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Idioms
+            take_archive:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+                    size: 3
+            filter_demand:
+                type: filter
+                params:
+                    by: label
+                    rhs: Demand
+            move_later:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Later
+            move_repeating:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Repeating
+            many:
+                type: condition
+                params:
+                    if:
+                        type: size
+                        op: gt
+                        value: 4
+                    then: move_later
+            any_demand:
+                type: condition
+                params:
+                    if:
+                        type: any
+                        filter: filter_demand
+                    then: move_later
+                    else: move_repeating
+            all_demand:
+                type: condition
+                params:
+                    if:
+                        type: all
+                        expr:
+                            label: Demand
+                    then: move_later
+                    else: move_repeating
+            any_moved:
+                type: condition
+                params:
+                    if:
+                        type: any
+                        filter: move_later
+                    then: move_repeating
+            idioms:
+                type: flow
+                params:
+                    - take_idioms
+                    - many
+            archive_any:
+                type: flow
+                params:
+                    - take_archive
+                    - any_demand
+            archive_all:
+                type: flow
+                params:
+                    - take_archive
+                    - all_demand
+        "#;
+
+    #[test]
+    fn condition() {
+        let mut e = executor(CONDITION);
+        e.start("idioms".to_string(), false).unwrap();
+        assert_eq!(e.connector.card_names("Later").len(), 5);
+
+        let mut e = executor(CONDITION);
+        e.start("archive_any".to_string(), false).unwrap();
+        assert_eq!(e.connector.card_names("Later").len(), 3);
+
+        let mut e = executor(CONDITION);
+        e.start("archive_all".to_string(), false).unwrap();
+        assert_eq!(e.connector.card_names("Repeating").len(), 3);
+
+        // the predicate cannot change the board
+        let mut e = executor(CONDITION);
+        assert!(e.start("any_moved".to_string(), false).is_err());
+
+        let e = &mut executor(CONDITION);
+        let many = e.ctx.task("many").unwrap();
+        let res = many.body.process(e, fixed_cards(4)).unwrap();
+        assert_eq!(names(&res), vec!["0", "1", "2", "3"]);
+        assert!(many.body.process(e, State::Init).is_err());
+    }
+
+    const FOREACH: &str = r#"
+            board: ENG
+            filter_demand:
+                type: filter
+                params:
+                    by: label
+                    rhs: Demand
+            move_later:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Later
+            move_nowhere:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Done
+            by_label:
+                type: condition
+                params:
+                    if:
+                        type: any
+                        filter: filter_demand
+                    then: move_later
+                    else: move_nowhere
+            each_demand:
+                type: foreach
+                params:
+                    task: filter_demand
+            each_stop:
+                type: foreach
+                params:
+                    task: by_label
+            each_continue:
+                type: foreach
+                params:
+                    task: by_label
+                    on_error: continue
+        "#;
+
+    #[test]
+    fn foreach() {
+        let e = &mut executor(FOREACH);
+        let archive = find_list(e, "Archive").unwrap().id;
+        let archive = e.cards_in_list(&archive).unwrap();
+        let cards = || State::Pipe(archive[..6].to_vec());
+
+        let each = e.ctx.task("each_demand").unwrap();
+        assert_eq!(
+            names(&each.body.process(e, cards()).unwrap()),
+            vec!["word 0", "word 3"]
+        );
+
+        let each = e.ctx.task("each_stop").unwrap();
+        assert!(each.body.process(e, cards()).is_err());
+        assert_eq!(e.connector.card_names("Later"), vec!["word 0"]);
+
+        let each = e.ctx.task("each_continue").unwrap();
+        let res = each.body.process(e, cards()).unwrap();
+        // the moved cards go on, the failed ones are skipped
+        assert_eq!(names(&res), vec!["word 0", "word 3"]);
+        assert_eq!(e.connector.card_names("Later").len(), 2);
+    }
+
+    #[test]
+    fn action_move() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_from_archive:
                 type: take
                 params:
                     from:
@@ -370,18 +1329,79 @@ mod tests {
                     -  shuffle
                     -  move              
         "#,
-            HashMap::new(),
-        )
-        .unwrap();
+        );
+        let task = e.ctx.task("flow").unwrap();
 
-        let mut e = Executor::new(ctx.clone(), trello(), HashMap::new()).unwrap();
-        let task = ctx.tasks.get("flow").unwrap().clone();
+        task.body.process(&mut e, State::Init).unwrap();
+        assert_eq!(e.connector.card_names("Repeating").len(), 10);
+        assert_eq!(e.connector.card_names("Archive").len(), 20);
+    }
 
-        let res = task.body.process(&mut e, State::Init).unwrap();
+    #[test]
+    fn action_chain() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_3:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+                    size: 3
+            move:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Later
+                        place: bottom
+            label:
+                type: action
+                params:
+                    type: labels
+                    op: add
+                    labels:
+                        - Later
+            print:
+                type: action
+                params:
+                    type: print
+            flow:
+                type: flow
+                params:
+                    -  take_3
+                    -  move
+                    -  label
+                    -  print
+            add:
+                type: action
+                params:
+                    type: add
+                    to:
+                        column: Later
+                    name: collapse
+        "#,
+        );
+        let later = find_list(&mut e, "Later").unwrap().id;
+        let label = e.label_by_name("Later", false).unwrap().unwrap().id;
+
+        let res = e.start("flow".to_string(), false).unwrap();
+        assert_eq!(names(&res), vec!["word 0", "word 1", "word 2"]);
+        assert!(res
+            .cards()
+            .unwrap()
+            .iter()
+            .all(|c| c.id_list == later && c.id_labels.contains(&label)));
+
+        let res = e.start("add".to_string(), false).unwrap();
+        assert_eq!(names(&res), vec!["collapse"]);
+        assert_eq!(e.connector.card_names("Later")[0], "collapse");
     }
+
     #[test]
     fn action_copy() {
-        let ctx = from_str(
+        let mut e = executor(
             r#"
             board: ENG
             take_from_archive:
@@ -403,21 +1423,628 @@ mod tests {
                     type: copy
                     to:
                         column: Repeating
-                        place: top
+                        place: bottom
+                    keep:
+                        - desc
+                        - labels
             flow:
                 type: flow
                 params:
                     -  take_from_archive
                     -  shuffle
                     -  move              
+        "#,
+        );
+        let task = e.ctx.task("flow").unwrap();
+
+        match e.start("flow".to_string(), true).unwrap() {
+            State::Plan(plan) => assert!(plan[0]
+                .to_string()
+                .ends_with("to Repeating at bottom with desc, labels")),
+            s => panic!("expected a plan, got {:?}", s),
+        }
+
+        let copies = task
+            .body
+            .process(&mut e, State::Init)
+            .unwrap()
+            .cards()
+            .unwrap();
+        assert_eq!(copies.len(), 10);
+        let archive = find_list(&mut e, "Archive").unwrap().id;
+        let archive = e.cards_in_list(&archive).unwrap();
+        for copy in copies.iter() {
+            let source = archive.iter().find(|c| c.name == copy.name).unwrap();
+            assert_ne!(source.id, copy.id);
+            assert_eq!(source.id_labels, copy.id_labels);
+        }
+        let copied = e.connector.card_names("Repeating");
+        let mut sorted = copied.clone();
+        sorted.sort();
+
+        assert_eq!(copied, sorted);
+        assert_eq!(copied.len(), 10);
+        assert_eq!(e.connector.card_names("Archive").len(), 30);
+    }
+
+    #[test]
+    fn action_add() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            new:
+                type: action
+                params:
+                    type: add
+                    to:
+                        column: Later
+                    name: collapse
+        "#,
+        );
+        e.start("new".to_string(), false).unwrap();
+        assert_eq!(e.connector.card_names("Later"), vec!["collapse"]);
+    }
+
+    const RANDOM_MOVE: &str = r#"
+            board: ENG
+            seed: 42
+            take_from_archive:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+                    size: 5
+            take_from_idioms:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Idioms
+            move:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Idioms
+                        place: random
+            flow:
+                type: flow
+                params:
+                    -  take_from_archive
+                    -  move
+            shuffle_idioms:
+                type: flow
+                params:
+                    -  take_from_idioms
+                    -  move
+        "#;
+
+    #[test]
+    fn action_move_random() {
+        let mut e = executor(RANDOM_MOVE);
+        e.start("flow".to_string(), false).unwrap();
+
+        let idioms = e.connector.card_names("Idioms");
+        assert_eq!(idioms.len(), 10);
+        assert_eq!(e.connector.card_names("Archive").len(), 25);
+
+        let mut same_seed = executor(RANDOM_MOVE);
+        same_seed.start("flow".to_string(), false).unwrap();
+        assert_eq!(same_seed.connector.card_names("Idioms"), idioms);
+
+        // moving the cards inside their own list shuffles it
+        e.start("shuffle_idioms".to_string(), false).unwrap();
+        let mut shuffled = e.connector.card_names("Idioms");
+        assert_ne!(shuffled, idioms);
+        shuffled.sort();
+        let mut idioms = idioms;
+        idioms.sort();
+        assert_eq!(shuffled, idioms);
+    }
+
+    #[test]
+    fn action_add_random() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            seed: 1
+            new:
+                type: action
+                params:
+                    type: add
+                    to:
+                        column: Idioms
+                        place: random
+                    name: collapse
+        "#,
+        );
+        e.start("new".to_string(), false).unwrap();
+        let idioms = e.connector.card_names("Idioms");
+        assert_eq!(idioms.len(), 6);
+        assert!(idioms.contains(&"collapse".to_string()));
+    }
+
+    #[test]
+    fn action_move_bottom_keeps_order() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_from_archive:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+                    size: 3
+            move:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Idioms
+                        place: bottom
+            flow:
+                type: flow
+                params:
+                    -  take_from_archive
+                    -  move
+        "#,
+        );
+        e.start("flow".to_string(), false).unwrap();
+        assert_eq!(
+            e.connector.card_names("Idioms")[5..],
+            ["word 0", "word 1", "word 2"]
+        );
+    }
+
+    #[test]
+    fn apply_order() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_from_idioms:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Idioms
+            reverse:
+                type: order
+                params:
+                    type: reverse
+            apply:
+                type: action
+                params:
+                    type: apply_order
+            flow:
+                type: flow
+                params:
+                    -  take_from_idioms
+                    -  reverse
+                    -  apply
+        "#,
+        );
+        // the last card stays in place
+        match e.start("flow".to_string(), true).unwrap() {
+            State::Plan(plan) => assert_eq!(plan.len(), 4),
+            s => panic!("expected a plan, got {:?}", s),
+        }
+
+        e.start("flow".to_string(), false).unwrap();
+        assert_eq!(
+            e.connector.card_names("Idioms"),
+            ["idiom 4", "idiom 3", "idiom 2", "idiom 1", "idiom 0"]
+        );
+    }
+
+    #[test]
+    fn archive_unarchive_delete() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_3:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+                    size: 3
+            take_archived:
+                type: take
+                params:
+                    from:
+                        type: archived_column
+                        source: Archive
+                    size: 2
+            take_all_archived:
+                type: take
+                params:
+                    from:
+                        type: archived_board
+                        source: ENG
+            archive:
+                type: action
+                params:
+                    type: archive
+            unarchive:
+                type: action
+                params:
+                    type: unarchive
+            delete:
+                type: action
+                params:
+                    type: delete
+                    confirm: true
+            archive_3:
+                type: flow
+                params:
+                    -  take_3
+                    -  archive
+            unarchive_2:
+                type: flow
+                params:
+                    -  take_archived
+                    -  unarchive
+            delete_archived:
+                type: flow
+                params:
+                    -  take_all_archived
+                    -  delete
+        "#,
+        );
+        match e.start("archive_3".to_string(), true).unwrap() {
+            State::Plan(plan) => assert_eq!(plan[0].to_string(), "archive 'word 0' in Archive"),
+            s => panic!("expected a plan, got {:?}", s),
+        }
+
+        e.start("archive_3".to_string(), false).unwrap();
+        assert_eq!(e.connector.card_names("Archive").len(), 27);
+        e.start("unarchive_2".to_string(), false).unwrap();
+        assert_eq!(e.connector.card_names("Archive")[..2], ["word 0", "word 1"]);
+        e.start("delete_archived".to_string(), false).unwrap();
+        assert_eq!(e.connector.card_names("Archive").len(), 29);
+        assert!(e.connector.archived_cards(&e.board_id).unwrap().is_empty());
+        // the snapshot follows the changes
+        let board_id = e.board_id.clone();
+        assert!(e.archived_cards(&board_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn label_actions() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_3:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+                    size: 3
+            add:
+                type: action
+                params:
+                    type: labels
+                    op: add
+                    labels:
+                        - Later
+                        - name: Fresh
+                          color: blue
+                    create: true
+            toggle:
+                type: action
+                params:
+                    type: labels
+                    op: toggle
+                    labels:
+                        - Demand
+            replace:
+                type: action
+                params:
+                    type: labels
+                    op: replace
+                    labels:
+                        - Mispronounced
+            remove:
+                type: action
+                params:
+                    type: labels
+                    op: remove
+                    labels:
+                        - Mispronounced
+            unknown:
+                type: action
+                params:
+                    type: labels
+                    op: add
+                    labels:
+                        - Fresh
+            add_3:
+                type: flow
+                params:
+                    -  take_3
+                    -  add
+            toggle_3:
+                type: flow
+                params:
+                    -  take_3
+                    -  toggle
+            replace_3:
+                type: flow
+                params:
+                    -  take_3
+                    -  replace
+            remove_3:
+                type: flow
+                params:
+                    -  take_3
+                    -  remove
+            unknown_3:
+                type: flow
+                params:
+                    -  take_3
+                    -  unknown
+        "#,
+        );
+        let labels = |e: &mut Executor<FakeBoard>| -> Vec<Vec<String>> {
+            let all = e.labels().unwrap();
+            let archive = find_list(e, "Archive").unwrap().id;
+            e.cards_in_list(&archive).unwrap()[..3]
+                .iter()
+                .map(|c| {
+                    all.iter()
+                        .filter(|l| c.id_labels.contains(&l.id))
+                        .map(|l| l.name.clone())
+                        .collect()
+                })
+                .collect()
+        };
+        let run = |e: &mut Executor<FakeBoard>, step: &str, dry_run: bool| {
+            e.start(format!("{}_3", step), dry_run)
+        };
+        assert!(run(&mut e, "unknown", false).is_err());
+
+        match run(&mut e, "add", true).unwrap() {
+            State::Plan(plan) => {
+                assert_eq!(plan.len(), 7);
+                assert_eq!(plan[0].to_string(), "create the blue label Fresh");
+                assert_eq!(plan[1].to_string(), "label 'word 0' with Later");
+            }
+            s => panic!("expected a plan, got {:?}", s),
+        }
+
+        let dir = journal_dir("label-actions");
+        e.journal_dir = Some(dir.clone());
+        run(&mut e, "add", false).unwrap();
+        let added = e.last_run().unwrap();
+        assert_eq!(
+            labels(&mut e),
+            [
+                vec!["Demand", "Later", "Fresh"],
+                vec!["Mispronounced", "Later", "Fresh"],
+                vec!["Later", "Fresh"]
+            ]
+        );
+        run(&mut e, "toggle", false).unwrap();
+        assert_eq!(
+            labels(&mut e),
+            [
+                vec!["Later", "Fresh"],
+                vec!["Demand", "Mispronounced", "Later", "Fresh"],
+                vec!["Demand", "Later", "Fresh"]
+            ]
+        );
+        run(&mut e, "replace", false).unwrap();
+        assert_eq!(labels(&mut e), vec![vec!["Mispronounced"]; 3]);
+        run(&mut e, "remove", false).unwrap();
+        assert_eq!(labels(&mut e), [Vec::<String>::new(), vec![], vec![]]);
+
+        e.revert(&e.last_run().unwrap()).unwrap();
+        assert_eq!(labels(&mut e), vec![vec!["Mispronounced"]; 3]);
+
+        // the created label is deleted with the run
+        e.revert(&added).unwrap();
+        let board_id = e.board_id.clone();
+        let names: Vec<String> = e
+            .connector
+            .labels(&board_id)
+            .unwrap()
+            .into_iter()
+            .map(|l| l.name)
+            .collect();
+        assert_eq!(names, ["Demand", "Mispronounced", "Later"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rewrite() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take_2:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Archive
+                    size: 2
+            rewrite:
+                type: action
+                params:
+                    type: rewrite
+                    name: "{{name}} ({{list}}, {{labels}})"
+                    desc:
+                        find: "^$"
+                        replace: "repeated {{date}}"
+            renumber:
+                type: action
+                params:
+                    type: rewrite
+                    name:
+                        find: "word (\\d+)"
+                        replace: "term $1"
+            flow:
+                type: flow
+                params:
+                    -  take_2
+                    -  rewrite
+            renumber_flow:
+                type: flow
+                params:
+                    -  take_2
+                    -  renumber
+        "#,
+        );
+        match e.start("flow".to_string(), true).unwrap() {
+            State::Plan(plan) => {
+                assert_eq!(plan.len(), 4);
+                assert_eq!(
+                    plan[0].to_string(),
+                    "rename 'word 0' to 'word 0 (Archive, Demand)'"
+                );
+                assert!(plan[1]
+                    .to_string()
+                    .starts_with("set the description of 'word 0' to 'repeated 20"));
+            }
+            s => panic!("expected a plan, got {:?}", s),
+        }
+        assert_eq!(e.connector.card_names("Archive")[0], "word 0");
+
+        e.start("flow".to_string(), false).unwrap();
+        e.start("renumber_flow".to_string(), false).unwrap();
+        assert_eq!(
+            e.connector.card_names("Archive")[..3],
+            [
+                "term 0 (Archive, Demand)",
+                "term 1 (Archive, Mispronounced)",
+                "word 2"
+            ]
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(19723), (2024, 1, 1));
+        assert_eq!(civil_date(19782), (2024, 2, 29));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn reposition_moves() {
+        assert!(reposition(&[1.0, 2.0, 3.0]).is_empty());
+        assert_eq!(reposition(&[1.0, 5.0, 2.0, 3.0]), vec![(1, 1.5)]);
+        assert_eq!(reposition(&[3.0, 1.0, 2.0]), vec![(0, 0.5)]);
+        assert_eq!(reposition(&[2.0, 3.0, 1.0]), vec![(2, 65539.0)]);
+        // no room between the neighbours
+        assert_eq!(
+            reposition(&[1.0, 0.0, 1.0 + f32::EPSILON]),
+            vec![(0, 65536.0), (1, 131072.0), (2, 196608.0)]
+        );
+    }
+
+    #[test]
+    fn missing_column() {
+        let mut e = executor(
+            r#"
+            board: ENG
+            take:
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Nowhere
+        "#,
+        );
+        assert!(e.start("take".to_string(), false).is_err());
+    }
+
+    #[test]
+    fn cross_board() {
+        let reading = eng()
+            .board("Reading")
+            .list("Inbox")
+            .list("Done")
+            .card("Done", "serendipity", &[])
+            .card("Done", "ubiquitous", &[])
+            .card("Inbox", "quaint", &[]);
+        let ctx = from_str(
+            r#"
+            board: ENG
+            harvest:
+                type: flow
+                params:
+                    - _take_done
+                    - _to_archive
+            _take_done:
+                board: Reading
+                type: take
+                params:
+                    from:
+                        type: column
+                        source: Done
+            _to_archive:
+                type: action
+                params:
+                    type: move
+                    to:
+                        column: Archive
+                        place: top
+            copy_back:
+                board: Reading
+                type: flow
+                params:
+                    - _take_inbox
+                    - _copy_to_later
+            _take_inbox:
+                type: take
+                params:
+                    from:
+                        type: board
+                        source: Reading
+                    size: 1
+            _copy_to_later:
+                type: action
+                params:
+                    type: copy
+                    to:
+                        board: ENG
+                        column: Later
         "#,
             HashMap::new(),
         )
         .unwrap();
+        let mut e = Executor::new(ctx, reading).unwrap();
+        let dir = journal_dir("cross-board");
+        e.journal_dir = Some(dir.clone());
 
-        let mut e = Executor::new(ctx.clone(), trello(), HashMap::new()).unwrap();
-        let task = ctx.tasks.get("flow").unwrap().clone();
+        match e.start("harvest".to_string(), true).unwrap() {
+            State::Plan(plan) => assert_eq!(plan.len(), 2),
+            s => panic!("expected a plan, got {:?}", s),
+        }
 
-        let res = task.body.process(&mut e, State::Init).unwrap();
+        let res = e.start("harvest".to_string(), false).unwrap();
+        assert_eq!(names(&res), ["serendipity", "ubiquitous"]);
+        assert!(e.connector.board_card_names("Reading", "Done").is_empty());
+        assert_eq!(
+            e.connector.board_card_names("ENG", "Archive")[..3],
+            ["ubiquitous", "serendipity", "word 0"]
+        );
+        // the moved cards are seen on their new board by the following runs
+        let archive = find_list(&mut e, "Archive").unwrap();
+        assert!(res.cards().unwrap().iter().all(|c| c.id_list == archive.id));
+
+        e.revert(&e.last_run().unwrap()).unwrap();
+        assert_eq!(
+            e.connector.board_card_names("Reading", "Done"),
+            ["serendipity", "ubiquitous"]
+        );
+        assert_eq!(e.connector.board_card_names("ENG", "Archive").len(), 30);
+
+        e.start("copy_back".to_string(), false).unwrap();
+        assert_eq!(e.connector.board_card_names("ENG", "Later"), ["quaint"]);
+        assert_eq!(e.connector.board_card_names("Reading", "Inbox"), ["quaint"]);
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
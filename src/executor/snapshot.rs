@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use crate::{
+    err::FlowError,
+    trello::{Card, Label, List, TrelloApi},
+};
+
+/// The lists, labels and cards of the boards, loaded on the first use
+/// and kept for the whole run of the executor.
+/// The cards changed by the actions are put back so the following steps see them.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    boards: HashMap<String, BoardSnapshot>,
+}
+
+#[derive(Debug, Default)]
+struct BoardSnapshot {
+    lists: Option<Vec<List>>,
+    labels: Option<Vec<Label>>,
+    cards: Option<Vec<Card>>,
+    archived: Option<Vec<Card>>,
+}
+
+impl Snapshot {
+    fn board(&mut self, board_id: &str) -> &mut BoardSnapshot {
+        self.boards.entry(board_id.to_string()).or_default()
+    }
+
+    pub fn lists<T: TrelloApi>(&mut self, api: &T, board_id: &str) -> Result<&[List], FlowError> {
+        let board = self.board(board_id);
+        if board.lists.is_none() {
+            info!("load the lists of the board {}", board_id);
+            board.lists = Some(api.lists(board_id)?);
+        }
+        Ok(board.lists.as_deref().unwrap_or_default())
+    }
+
+    pub fn labels<T: TrelloApi>(&mut self, api: &T, board_id: &str) -> Result<&[Label], FlowError> {
+        let board = self.board(board_id);
+        if board.labels.is_none() {
+            info!("load the labels of the board {}", board_id);
+            board.labels = Some(api.labels(board_id)?);
+        }
+        Ok(board.labels.as_deref().unwrap_or_default())
+    }
+
+    pub fn cards<T: TrelloApi>(&mut self, api: &T, board_id: &str) -> Result<&[Card], FlowError> {
+        let board = self.board(board_id);
+        if board.cards.is_none() {
+            info!("load the cards of the board {}", board_id);
+            board.cards = Some(api.cards(board_id)?);
+        }
+        Ok(board.cards.as_deref().unwrap_or_default())
+    }
+
+    pub fn archived<T: TrelloApi>(
+        &mut self,
+        api: &T,
+        board_id: &str,
+    ) -> Result<&[Card], FlowError> {
+        let board = self.board(board_id);
+        if board.archived.is_none() {
+            info!("load the archived cards of the board {}", board_id);
+            board.archived = Some(api.archived_cards(board_id)?);
+        }
+        Ok(board.archived.as_deref().unwrap_or_default())
+    }
+
+    /// The board of the list among the loaded ones.
+    pub fn board_of_list(&self, list_id: &str) -> Option<&str> {
+        self.boards
+            .iter()
+            .find(|(_, b)| b.lists.iter().flatten().any(|l| l.id == list_id))
+            .map(|(id, _)| id.as_str())
+    }
+
+    /// The list with the id among the loaded ones of all the boards.
+    pub fn list(&self, list_id: &str) -> Option<&List> {
+        self.boards
+            .values()
+            .flat_map(|b| b.lists.iter().flatten())
+            .find(|l| l.id == list_id)
+    }
+
+    /// Replaces the card with the same id or adds a new one to its board,
+    /// the archived cards go to the archived ones.
+    /// Nothing happens to the cards that have not been loaded yet.
+    pub fn update(&mut self, card: &Card) {
+        self.remove(&card.id);
+        if let Some(board) = self.boards.get_mut(&card.id_board) {
+            let cards = if card.closed {
+                &mut board.archived
+            } else {
+                &mut board.cards
+            };
+            if let Some(cards) = cards.as_mut() {
+                cards.push(card.clone());
+            }
+        }
+    }
+
+    /// Adds the created label if the labels of the board have been loaded.
+    pub fn add_label(&mut self, board_id: &str, label: &Label) {
+        if let Some(labels) = self.board(board_id).labels.as_mut() {
+            labels.push(label.clone());
+        }
+    }
+
+    /// Drops the deleted card.
+    pub fn remove(&mut self, card_id: &str) {
+        for board in self.boards.values_mut() {
+            for cards in [&mut board.cards, &mut board.archived] {
+                if let Some(cards) = cards.as_mut() {
+                    cards.retain(|c| c.id != card_id);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Snapshot::default();
+    }
+}
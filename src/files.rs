@@ -11,5 +11,5 @@ pub fn read_file_into_string(path: &str) -> Result<String, Error> {
 }
 
 pub fn yml_str_to<'a, T: Deserialize<'a>>(from: &'a str) -> Result<T, FlowError> {
-    serde_yaml::from_str(&from).map_err(|e| e.into())
+    serde_yaml::from_str(from).map_err(|e| e.into())
 }
@@ -1,3 +1,5 @@
 pub mod context;
+mod marks;
 pub mod parse;
 pub mod tasks;
+pub mod validate;
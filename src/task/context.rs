@@ -1,24 +1,18 @@
 use std::collections::HashMap;
-use std::fmt::format;
-use std::io::{Error, Read};
 
-use serde::de::Visitor;
-use serde::{Deserialize, Deserializer};
 use yaml_rust::YamlLoader;
 
-use super::parse::{as_string, ParametrizedYaml};
-use super::tasks::{
-    ActionTask, FilterTask, FlowTask, GroupTask, OrderTask, Place, Source, TakeTask, Target, Task,
-    TaskBody,
-};
+use super::marks::Marks;
+use super::parse::{as_i64, as_string, field_by_name, or_default, ParametrizedYaml};
+use super::tasks::Task;
 use crate::err::FlowError;
 use crate::executor::error;
-use crate::files::read_file_into_string;
-use crate::trello::Card;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct TaskContext {
     pub board: String,
+    /// makes the random choices of the run reproducible
+    pub seed: Option<u64>,
     pub tasks: HashMap<String, Task>,
 }
 
@@ -26,48 +20,53 @@ impl TaskContext {
     pub fn task(&self, name: &str) -> Result<Task, FlowError> {
         self.tasks
             .get(name)
-            .map(Clone::clone)
-            .ok_or(error(format!("the task {} does not exist", name)))
+            .cloned()
+            .ok_or_else(|| error(format!("the task {} does not exist", name)))
     }
 }
 
-impl Default for TaskContext {
-    fn default() -> Self {
-        Self {
-            board: Default::default(),
-            tasks: Default::default(),
-        }
-    }
+/// Parses the tasks, the errors of the fields point to the line and the column in the file.
+pub fn from_str(yml: &str, arguments: HashMap<String, String>) -> Result<TaskContext, FlowError> {
+    parse(yml, arguments).map_err(|e| Marks::from_str(yml).locate(e))
 }
 
-pub fn from_str(yml: &str, arguments: HashMap<String, String>) -> Result<TaskContext, FlowError> {
-    let yamls = YamlLoader::load_from_str(&yml)?;
+fn parse(yml: &str, arguments: HashMap<String, String>) -> Result<TaskContext, FlowError> {
+    let yamls = YamlLoader::load_from_str(yml)?;
     let yaml = yamls
         .first()
         .and_then(|s| s.as_hash())
-        .ok_or(FlowError::SerdeError(format!(
-            "the yaml with tasks seems to be absent in {}",
-            yml,
-        )))?;
+        .ok_or(FlowError::SerdeError(
+            "the tasks file should be a map of the tasks".to_string(),
+        ))?;
 
     let mut tasks: HashMap<String, Task> = HashMap::new();
     let mut board = String::new();
+    let mut seed = None;
 
     for (k, v) in yaml.into_iter() {
         match as_string(ParametrizedYaml::new(k, arguments.clone()))?.as_str() {
-            "board" => board = as_string(ParametrizedYaml::new(v, arguments.clone()))?.to_string(),
+            "board" => board = as_string(ParametrizedYaml::new(v, arguments.clone()).at("board"))?,
+            "seed" => {
+                seed = Some(as_i64(ParametrizedYaml::new(v, arguments.clone()).at("seed"))? as u64)
+            }
             e => {
-                let body: TaskBody = ParametrizedYaml::new(v, arguments.clone()).try_into()?;
+                let yml = ParametrizedYaml::new(v, arguments.clone()).at(e);
                 let task = Task {
                     name: e.to_string(),
-                    body,
+                    board: or_default(
+                        field_by_name("board", yml.clone())
+                            .and_then(as_string)
+                            .map(Some),
+                        None,
+                    )?,
+                    body: yml.try_into()?,
                 };
                 tasks.insert(e.to_string(), task);
             }
         }
     }
 
-    Ok(TaskContext { board, tasks })
+    Ok(TaskContext { board, seed, tasks })
 }
 
 #[cfg(test)]
@@ -75,121 +74,144 @@ mod tests {
     use std::collections::HashMap;
 
     use crate::{
+        err::{FieldError, FlowError},
         files::read_file_into_string,
-        task::{tasks::*, *},
+        task::tasks::*,
     };
 
     use super::from_str;
 
     #[test]
     fn test() {
-        let yml_content =
-            read_file_into_string("/home/bzhg/projects/trello-vocab-loader/examples/task.yml")
-                .unwrap();
+        let yml_content = read_file_into_string("examples/task.yml").unwrap();
         let ctx = from_str(&yml_content, HashMap::new()).unwrap();
         assert_eq!(ctx.board, "ENG".to_string());
 
+        assert_eq!(
+            ctx.tasks["_shuffle_idioms"],
+            Task {
+                name: "_shuffle_idioms".to_string(),
+                board: None,
+                body: TaskBody::Order(OrderTask::Shuffle(Source::Column("Idioms".to_string())))
+            }
+        );
         assert_eq!(
             ctx.tasks["shuffle_idioms"],
             Task {
                 name: "shuffle_idioms".to_string(),
-                body: TaskBody::Order(OrderTask::Shuffle(Source::Column("Idioms".to_string())))
+                board: None,
+                body: TaskBody::Flow(FlowTask {
+                    steps: vec!["_shuffle_idioms".to_string(), "_apply_order".to_string()]
+                })
             }
         );
         assert_eq!(
-            ctx.tasks["take_from_archive"],
+            ctx.tasks["_take_from_archive"],
             Task {
-                name: "take_from_archive".to_string(),
+                name: "_take_from_archive".to_string(),
+                board: None,
                 body: TaskBody::Take(TakeTask {
-                    src: Source::Column("archive".to_string()),
+                    src: Source::Column("Archive".to_string()),
                     size: 0,
+                    skip: 0,
                     place: Place::Top
                 })
             }
         );
         assert_eq!(
-            ctx.tasks["filter_demand"],
+            ctx.tasks["_filter_demand"],
             Task {
-                name: "filter_demand".to_string(),
-                body: TaskBody::Filter(FilterTask::Label("demand".to_string(), true))
+                name: "_filter_demand".to_string(),
+                board: None,
+                body: TaskBody::Filter(FilterTask::Label("Demand".to_string(), true))
             }
         );
         assert_eq!(
-            ctx.tasks["filter_mispronounced"],
+            ctx.tasks["_filter_mispronounced"],
             Task {
-                name: "filter_mispronounced".to_string(),
-                body: TaskBody::Filter(FilterTask::Label("mispronounced".to_string(), true))
+                name: "_filter_mispronounced".to_string(),
+                board: None,
+                body: TaskBody::Filter(FilterTask::Label("Mispronounced".to_string(), true))
             }
         );
         assert_eq!(
-            ctx.tasks["take_5"],
+            ctx.tasks["_take_5"],
             Task {
-                name: "take_5".to_string(),
+                name: "_take_5".to_string(),
+                board: None,
                 body: TaskBody::Take(TakeTask {
                     src: Source::Pipe,
                     size: 5,
+                    skip: 0,
                     place: Place::Random
                 })
             }
         );
         assert_eq!(
-            ctx.tasks["take_10"],
+            ctx.tasks["_take_10"],
             Task {
-                name: "take_10".to_string(),
+                name: "_take_10".to_string(),
+                board: None,
                 body: TaskBody::Take(TakeTask {
                     src: Source::Pipe,
                     size: 10,
+                    skip: 0,
                     place: Place::Random
                 })
             }
         );
         assert_eq!(
-            ctx.tasks["move_to_repeat"],
+            ctx.tasks["_move_to_repeat"],
             Task {
-                name: "move_to_repeat".to_string(),
+                name: "_move_to_repeat".to_string(),
+                board: None,
                 body: TaskBody::Action(ActionTask::MoveToColumn(Target {
-                    column: "repeat".to_string(),
-                    place: Place::Top
+                    column: "Repeating".to_string(),
+                    place: Place::Top,
+                    board: None,
                 }))
             }
         );
         assert_eq!(
-            ctx.tasks["repeat_demand"],
+            ctx.tasks["_repeat_demand"],
             Task {
-                name: "repeat_demand".to_string(),
+                name: "_repeat_demand".to_string(),
+                board: None,
                 body: TaskBody::Flow(FlowTask {
                     steps: vec![
-                        "take_from_archive".to_string(),
-                        "filter_demand".to_string(),
-                        "take_5".to_string(),
-                        "move_to_repeat".to_string(),
+                        "_take_from_archive".to_string(),
+                        "_filter_demand".to_string(),
+                        "_take_5".to_string(),
+                        "_move_to_repeat".to_string(),
                     ]
                 })
             }
         );
         assert_eq!(
-            ctx.tasks["repeat_mispronounced"],
+            ctx.tasks["_repeat_mispronounced"],
             Task {
-                name: "repeat_mispronounced".to_string(),
+                name: "_repeat_mispronounced".to_string(),
+                board: None,
                 body: TaskBody::Flow(FlowTask {
                     steps: vec![
-                        "take_from_archive".to_string(),
-                        "filter_mispronounced".to_string(),
-                        "take_5".to_string(),
-                        "move_to_repeat".to_string(),
+                        "_take_from_archive".to_string(),
+                        "_filter_mispronounced".to_string(),
+                        "_take_5".to_string(),
+                        "_move_to_repeat".to_string(),
                     ]
                 })
             }
         );
         assert_eq!(
-            ctx.tasks["repeat_others"],
+            ctx.tasks["_repeat_others"],
             Task {
-                name: "repeat_others".to_string(),
+                name: "_repeat_others".to_string(),
+                board: None,
                 body: TaskBody::Flow(FlowTask {
                     steps: vec![
-                        "take_from_archive".to_string(),
-                        "take_10".to_string(),
-                        "move_to_repeat".to_string(),
+                        "_take_from_archive".to_string(),
+                        "_take_10".to_string(),
+                        "_move_to_repeat".to_string(),
                     ]
                 })
             }
@@ -198,15 +220,57 @@ mod tests {
             ctx.tasks["repeat"],
             Task {
                 name: "repeat".to_string(),
+                board: None,
                 body: TaskBody::Group(GroupTask {
                     steps: vec![
-                        "repeat_others".to_string(),
-                        "repeat_mispronounced".to_string(),
-                        "repeat_demand".to_string(),
+                        "_repeat_others".to_string(),
+                        "_repeat_mispronounced".to_string(),
+                        "_repeat_demand".to_string(),
                         "shuffle_idioms".to_string(),
                     ]
                 })
             }
         );
     }
+
+    #[test]
+    fn errors() {
+        let yml = r#"board: ENG
+_take:
+  type: take
+  params:
+    size: ten
+_move_to_repeat:
+  type: action
+  params:
+    type: move
+    to:
+      place: top
+"#;
+        let field = |yml: &str| match from_str(yml, HashMap::new()) {
+            Err(FlowError::NoFieldError(e)) | Err(FlowError::FieldError(e)) => e,
+            r => panic!("expected a field error, got {:?}", r),
+        };
+
+        let absent = FieldError {
+            path: "_move_to_repeat.params.to.column".to_string(),
+            msg: "the field is absent".to_string(),
+            mark: Some((10, 5)),
+        };
+        let wrong = FieldError {
+            path: "_take.params.size".to_string(),
+            msg: "should be a number but got 'ten'".to_string(),
+            mark: Some((5, 5)),
+        };
+        assert_eq!(field(yml), wrong);
+        assert_eq!(field(&yml.replace("ten", "10")), absent);
+        assert_eq!(
+            absent.to_string(),
+            "the task '_move_to_repeat', _move_to_repeat.params.to.column (line 10, column 5): the field is absent"
+        );
+        assert_eq!(
+            field(&yml.replace("ten", "10").replace("type: move", "type: jump")).path,
+            "_move_to_repeat.params.type"
+        );
+    }
 }
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use yaml_rust::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+use crate::err::FlowError;
+
+enum Frame {
+    /// the path of the map and the key waiting for its value
+    Map(String, Option<String>),
+    /// the path of the sequence and the index of the next element
+    Seq(String, usize),
+}
+
+/// The line and the column of every field of a yaml file by its path like `task.params.to`,
+/// the keys of the maps are marked by the position of the key.
+#[derive(Default)]
+pub struct Marks {
+    stack: Vec<Frame>,
+    marks: HashMap<String, (usize, usize)>,
+}
+
+impl Marks {
+    pub fn from_str(yml: &str) -> Marks {
+        let mut marks = Marks::default();
+        // the errors of the yaml itself are reported by the loader
+        let _ = Parser::new(yml.chars()).load(&mut marks, false);
+        marks
+    }
+
+    /// the mark of the field or of the closest enclosing field
+    pub fn find(&self, path: &str) -> Option<(usize, usize)> {
+        let mut path = path;
+        loop {
+            if let Some(mark) = self.marks.get(path) {
+                return Some(*mark);
+            }
+            path = &path[..path.rfind(['.', '['])?];
+        }
+    }
+
+    /// sets the position of the field to the parsing errors
+    pub fn locate(&self, error: FlowError) -> FlowError {
+        match error {
+            FlowError::NoFieldError(mut e) => {
+                e.mark = self.find(&e.path);
+                FlowError::NoFieldError(e)
+            }
+            FlowError::FieldError(mut e) => {
+                e.mark = self.find(&e.path);
+                FlowError::FieldError(e)
+            }
+            e => e,
+        }
+    }
+
+    fn mark(&mut self, path: &str, mark: Marker) {
+        self.marks
+            .entry(path.to_string())
+            .or_insert((mark.line(), mark.col() + 1));
+    }
+
+    fn value(&mut self, mark: Marker) -> String {
+        let path = match self.stack.last_mut() {
+            Some(Frame::Map(path, key)) => format!("{}.{}", path, key.take().unwrap_or_default()),
+            Some(Frame::Seq(path, idx)) => {
+                *idx += 1;
+                format!("{}[{}]", path, *idx - 1)
+            }
+            None => String::new(),
+        };
+        let path = path.trim_start_matches('.').to_string();
+        self.mark(&path, mark);
+        path
+    }
+}
+
+impl MarkedEventReceiver for Marks {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        match ev {
+            Event::Scalar(v, ..) => match self.stack.last_mut() {
+                Some(Frame::Map(path, key)) if key.is_none() => {
+                    let path = format!("{}.{}", path, v);
+                    *key = Some(v);
+                    self.mark(path.trim_start_matches('.'), mark);
+                }
+                _ => {
+                    self.value(mark);
+                }
+            },
+            Event::Alias(_) => {
+                self.value(mark);
+            }
+            Event::MappingStart(_) => {
+                let path = self.value(mark);
+                self.stack.push(Frame::Map(path, None));
+            }
+            Event::SequenceStart(_) => {
+                let path = self.value(mark);
+                self.stack.push(Frame::Seq(path, 0));
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Marks;
+
+    #[test]
+    fn marks() {
+        let marks = Marks::from_str(
+            r#"board: ENG
+repeat:
+  type: group
+  params:
+    - a
+    - b
+_move:
+  type: action
+  params:
+    type: move
+    to:
+      place: top
+"#,
+        );
+        assert_eq!(marks.find("board"), Some((1, 1)));
+        assert_eq!(marks.find("repeat.params"), Some((4, 3)));
+        assert_eq!(marks.find("repeat.params[1]"), Some((6, 7)));
+        assert_eq!(marks.find("_move.params.to.column"), Some((11, 5)));
+        assert_eq!(marks.find("unknown"), None);
+    }
+}
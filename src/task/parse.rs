@@ -1,32 +1,57 @@
-use std::{collections::HashMap, hash::Hash, vec};
+use std::collections::HashMap;
 
-use crate::err::{self, FlowError};
+use crate::err::{FieldError, FlowError};
 
+use regex::{Regex, RegexBuilder};
 use yaml_rust::Yaml;
 
 use super::tasks::{
-    ActionTask, CardInfo, FilterTask, FlowTask, GroupTask, OrderTask, Place, Source, TakeTask,
-    Target, Task, TaskBody,
+    ActionTask, CardInfo, Check, Comparison, ConditionTask, CopyField, ErrorPolicy, Expr,
+    FilterTask, FlowTask, ForEachTask, GroupTask, LabelAction, LabelFilter, LabelOp, LabelRef,
+    MatchMode, NewLabel, OrderTask, Place, Predicate, RewriteAction, Rewriting, SortField, SortKey,
+    Source, TakeTask, Target, TaskBody, Template, Text, TEMPLATE_FIELDS,
 };
 
 #[derive(Clone)]
 pub struct ParametrizedYaml<'a> {
     pub yaml: &'a Yaml,
     pub arguments: HashMap<String, String>,
+    /// the path of the node in the file like `task.params.to`, used in the errors
+    pub path: String,
 }
 
 impl<'a> From<&'a Yaml> for ParametrizedYaml<'a> {
     fn from(value: &'a Yaml) -> Self {
-        ParametrizedYaml {
-            yaml: value,
-            arguments: HashMap::new(),
-        }
+        ParametrizedYaml::new(value, HashMap::new())
     }
 }
 
 impl<'a> ParametrizedYaml<'a> {
     pub fn new(yaml: &'a Yaml, arguments: HashMap<String, String>) -> Self {
-        Self { yaml, arguments }
+        Self {
+            yaml,
+            arguments,
+            path: String::new(),
+        }
+    }
+
+    pub fn at(self, path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            ..self
+        }
+    }
+
+    fn field_path(&self, name: &str) -> String {
+        if self.path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.path, name)
+        }
+    }
+
+    fn error<T>(&self, msg: String) -> Result<T, FlowError> {
+        Err(FlowError::FieldError(FieldError::new(&self.path, msg)))
     }
 }
 
@@ -35,11 +60,13 @@ impl<'a> TryFrom<ParametrizedYaml<'a>> for Source {
 
     fn try_from(value: ParametrizedYaml<'a>) -> Result<Self, Self::Error> {
         let src = field_by_name("source", value.clone()).and_then(as_string)?;
-        match tpe(value)?.as_str() {
+        match tpe(value.clone())?.as_str() {
             "pipe" => Ok(Source::Pipe),
-            "board" => Ok(Source::Board),
+            "board" => Ok(Source::Board(src.to_string())),
             "column" => Ok(Source::Column(src.to_string())),
-            e => error(e),
+            "archived_board" => Ok(Source::ArchivedBoard(src.to_string())),
+            "archived_column" => Ok(Source::ArchivedColumn(src.to_string())),
+            e => error(&value, "type", e),
         }
     }
 }
@@ -48,13 +75,13 @@ impl<'a> TryFrom<ParametrizedYaml<'a>> for Place {
     type Error = FlowError;
 
     fn try_from(value: ParametrizedYaml<'a>) -> Result<Self, Self::Error> {
-        field_by_name("place", value)
+        field_by_name("place", value.clone())
             .and_then(as_string)
             .and_then(|s| match s.as_str() {
                 "top" => Ok(Place::Top),
                 "bottom" => Ok(Place::Bottom),
                 "random" => Ok(Place::Random),
-                _ => error("place"),
+                e => error(&value, "place", e),
             })
     }
 }
@@ -65,19 +92,260 @@ impl<'a> TryFrom<ParametrizedYaml<'a>> for Target {
             .and_then(as_string)
             .map(|s| s.to_string())?;
 
-        let place = or_default(value.try_into(), Place::Top)?;
+        let place = or_default(value.clone().try_into(), Place::Top)?;
+        let board = or_default(
+            field_by_name("board", value).and_then(as_string).map(Some),
+            None,
+        )?;
+
+        Ok(Target {
+            column,
+            place,
+            board,
+        })
+    }
+}
+impl<'a> TryFrom<ParametrizedYaml<'a>> for Predicate {
+    type Error = FlowError;
 
-        Ok(Target { column, place })
+    fn try_from(value: ParametrizedYaml<'a>) -> Result<Self, Self::Error> {
+        match tpe(value.clone())?.as_str() {
+            "empty" => Ok(Predicate::Empty),
+            "size" => {
+                let (op, size) = comparison(value)?;
+                Ok(Predicate::Size(op, size))
+            }
+            "any" => Ok(Predicate::Any(value.try_into()?)),
+            "all" => Ok(Predicate::All(value.try_into()?)),
+            e => error(&value, "type", e),
+        }
     }
 }
-fn error<T>(t: &str) -> Result<T, FlowError> {
-    Err(FlowError::UnexpectedValueError(format!(
-        "the type '{}' is not recognized",
-        t
+
+/// `filter: task` or `expr: ...`, the predicate should not change the board.
+impl<'a> TryFrom<ParametrizedYaml<'a>> for Check {
+    type Error = FlowError;
+
+    fn try_from(value: ParametrizedYaml<'a>) -> Result<Self, Self::Error> {
+        match (
+            field_by_name("filter", value.clone()),
+            field_by_name("expr", value.clone()),
+        ) {
+            (Ok(filter), Err(_)) => Ok(Check::Filter(as_string(filter)?)),
+            (Err(_), Ok(expr)) => Ok(Check::Expr(expr.try_into()?)),
+            (Ok(_), Ok(_)) => {
+                value.error("either filter or expr is expected, not both".to_string())
+            }
+            (Err(e), Err(_)) => Err(e),
+        }
+    }
+}
+
+/// A key like `due` or `due desc`.
+impl<'a> TryFrom<ParametrizedYaml<'a>> for SortKey {
+    type Error = FlowError;
+
+    fn try_from(value: ParametrizedYaml<'a>) -> Result<Self, Self::Error> {
+        let key = as_string(value.clone())?;
+        let mut words = key.split_whitespace();
+        let field = match words.next().unwrap_or_default() {
+            "pos" => SortField::Pos,
+            "name" => SortField::Name,
+            "desc_length" => SortField::DescLength,
+            "activity" => SortField::Activity,
+            "due" => SortField::Due,
+            "created" => SortField::Created,
+            "labels" => SortField::Labels,
+            e => return value.error(format!("the sort key '{}' is not recognized", e)),
+        };
+        let desc = match (words.next(), words.next()) {
+            (None | Some("asc"), None) => false,
+            (Some("desc"), None) => true,
+            _ => return value.error("the direction should be asc or desc".to_string()),
+        };
+        Ok(SortKey { field, desc })
+    }
+}
+
+/// `op` and `value` fields like `op: gt` and `value: 5`.
+fn comparison(yml: ParametrizedYaml) -> Result<(Comparison, usize), FlowError> {
+    let op = match field_by_name("op", yml.clone())
+        .and_then(as_string)?
+        .as_str()
+    {
+        "eq" => Comparison::Eq,
+        "ne" => Comparison::Ne,
+        "gt" => Comparison::Gt,
+        "ge" => Comparison::Ge,
+        "lt" => Comparison::Lt,
+        "le" => Comparison::Le,
+        e => return error(&yml, "op", e),
+    };
+    let value = field_by_name("value", yml).and_then(as_i64)? as usize;
+    Ok((op, value))
+}
+
+const EXPR_KEYS: [&str; 10] = [
+    "and",
+    "or",
+    "not",
+    "name",
+    "desc",
+    "label",
+    "color",
+    "no_labels",
+    "list",
+    "pos",
+];
+
+impl<'a> TryFrom<ParametrizedYaml<'a>> for Expr {
+    type Error = FlowError;
+
+    fn try_from(value: ParametrizedYaml<'a>) -> Result<Self, Self::Error> {
+        let keys: Vec<&str> = EXPR_KEYS
+            .iter()
+            .copied()
+            .filter(|k| field_by_name(k, value.clone()).is_ok())
+            .collect();
+        let key = match keys[..] {
+            [key] => key,
+            [] => return value.error(format!("should have one of {}", EXPR_KEYS.join(", "))),
+            _ => {
+                return value.error(format!(
+                    "should have only one of {}, wrap them in and",
+                    keys.join(", ")
+                ))
+            }
+        };
+        let field = field_by_name(key, value.clone())?;
+        let case = || {
+            or_default(
+                field_by_name("case", value.clone()).and_then(as_bool),
+                false,
+            )
+        };
+        let exprs = || -> Result<Vec<Expr>, FlowError> {
+            elements(field.clone())?
+                .into_iter()
+                .map(Expr::try_from)
+                .collect()
+        };
+        match key {
+            "and" => Ok(Expr::And(exprs()?)),
+            "or" => Ok(Expr::Or(exprs()?)),
+            "not" => Ok(Expr::Not(Box::new(field.clone().try_into()?))),
+            "name" => Ok(Expr::Name(text(
+                as_string(field.clone())?,
+                value.clone(),
+                case()?,
+            )?)),
+            "desc" => Ok(Expr::Desc(text(
+                as_string(field.clone())?,
+                value.clone(),
+                case()?,
+            )?)),
+            "label" => Ok(Expr::Label(as_string(field.clone())?, case()?)),
+            "color" => Ok(Expr::Color(as_string(field.clone())?)),
+            "no_labels" if as_bool(field.clone())? => Ok(Expr::NoLabels),
+            "no_labels" => Ok(Expr::Not(Box::new(Expr::NoLabels))),
+            "list" => Ok(Expr::List(as_string(field.clone())?)),
+            _ => {
+                let (op, value) = comparison(field.clone())?;
+                Ok(Expr::Pos(op, value))
+            }
+        }
+    }
+}
+
+/// The pattern with the `mode` field next to it, the exact match by default.
+fn text(pattern: String, yml: ParametrizedYaml, case: bool) -> Result<Text, FlowError> {
+    let mode = or_default(
+        field_by_name("mode", yml.clone()).and_then(as_string),
+        "exact".to_string(),
+    )?;
+    let mode = match mode.as_str() {
+        "exact" => MatchMode::Exact,
+        "contains" => MatchMode::Contains,
+        "starts_with" => MatchMode::StartsWith,
+        "regex" => RegexBuilder::new(&pattern)
+            .case_insensitive(!case)
+            .build()
+            .map(MatchMode::Regex)
+            .or_else(|e| yml.error(format!("the regex '{}' is not valid: {}", pattern, e)))?,
+        "fuzzy" => {
+            let distance = or_default(field_by_name("distance", yml.clone()).and_then(as_i64), 2)?;
+            if distance < 0 {
+                return Err(FlowError::FieldError(FieldError::new(
+                    &yml.field_path("distance"),
+                    format!("the distance {} should not be negative", distance),
+                )));
+            }
+            MatchMode::Fuzzy(distance as usize)
+        }
+        e => return error(&yml, "mode", e),
+    };
+    Ok(Text {
+        pattern,
+        mode,
+        case,
+    })
+}
+
+/// A template checked for the unknown fields.
+fn template(yml: ParametrizedYaml) -> Result<Template, FlowError> {
+    let template = Template(as_string(yml.clone())?);
+    match template
+        .fields()
+        .into_iter()
+        .find(|f| !TEMPLATE_FIELDS.contains(&f.as_str()))
+    {
+        Some(f) => yml.error(format!(
+            "the field {{{{{}}}}} is unknown, the known ones are {}",
+            f,
+            TEMPLATE_FIELDS.join(", ")
+        )),
+        None => Ok(template),
+    }
+}
+
+/// A template or a `find` regex with the `replace` template, none if the field is absent.
+fn rewriting(name: &str, yml: ParametrizedYaml) -> Result<Option<Rewriting>, FlowError> {
+    let yml = match field_by_name(name, yml) {
+        Ok(yml) => yml,
+        Err(FlowError::NoFieldError(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match field_by_name("find", yml.clone()) {
+        Ok(find) => {
+            let pattern = as_string(find.clone())?;
+            let regex = Regex::new(&pattern)
+                .or_else(|e| find.error(format!("the regex '{}' is not valid: {}", pattern, e)))?;
+            let replace = field_by_name("replace", yml).and_then(template)?;
+            Ok(Some(Rewriting::Replace(regex, replace)))
+        }
+        Err(_) => template(yml).map(|t| Some(Rewriting::Template(t))),
+    }
+}
+
+/// A list of label names or `color: name` maps, empty if the field is absent.
+fn label_refs(name: &str, yml: ParametrizedYaml) -> Result<Vec<LabelRef>, FlowError> {
+    or_default(field_by_name(name, yml).and_then(elements), vec![])?
+        .into_iter()
+        .map(|e| match field_by_name("color", e.clone()) {
+            Ok(color) => as_string(color).map(LabelRef::Color),
+            Err(_) => as_string(e).map(LabelRef::Name),
+        })
+        .collect()
+}
+
+fn error<T>(yml: &ParametrizedYaml, field: &str, value: &str) -> Result<T, FlowError> {
+    Err(FlowError::FieldError(FieldError::new(
+        &yml.field_path(field),
+        format!("the value '{}' is not recognized", value),
     )))
 }
 
-fn or_default<T>(e: Result<T, FlowError>, default: T) -> Result<T, FlowError> {
+pub fn or_default<T>(e: Result<T, FlowError>, default: T) -> Result<T, FlowError> {
     match e {
         Err(FlowError::NoFieldError(_)) => Ok(default),
         err => err,
@@ -89,7 +357,7 @@ impl<'a> TryFrom<ParametrizedYaml<'a>> for TaskBody {
 
     fn try_from(value: ParametrizedYaml<'a>) -> Result<Self, Self::Error> {
         let task_type = tpe(value.clone())?;
-        let params = params(value)?;
+        let params = params(value.clone())?;
 
         match task_type.as_str() {
             "group" => Ok(TaskBody::Group(GroupTask {
@@ -102,9 +370,46 @@ impl<'a> TryFrom<ParametrizedYaml<'a>> for TaskBody {
                 let action_type = tpe(params.clone())?;
                 match action_type.as_str() {
                     "print" => Ok(TaskBody::Action(ActionTask::PrintToConsole)),
+                    "refresh" => Ok(TaskBody::Action(ActionTask::Refresh)),
+                    "apply_order" => Ok(TaskBody::Action(ActionTask::ApplyOrder)),
+                    "archive" => Ok(TaskBody::Action(ActionTask::Archive)),
+                    "unarchive" => Ok(TaskBody::Action(ActionTask::Unarchive)),
+                    "delete" => {
+                        // the deleted cards cannot be brought back
+                        if or_default(
+                            field_by_name("confirm", params.clone()).and_then(as_bool),
+                            false,
+                        )? {
+                            Ok(TaskBody::Action(ActionTask::Delete))
+                        } else {
+                            Err(FlowError::FieldError(FieldError::new(
+                                &params.field_path("confirm"),
+                                "the cards are deleted for good only with confirm: true"
+                                    .to_string(),
+                            )))
+                        }
+                    }
                     "copy" => {
-                        let to: Target = field_by_name("to", params).and_then(|y| y.try_into())?;
-                        Ok(TaskBody::Action(ActionTask::CopyToColumn(to)))
+                        let to: Target =
+                            field_by_name("to", params.clone()).and_then(|y| y.try_into())?;
+                        let keep =
+                            or_default(field_by_name("keep", params).and_then(elements), vec![])?
+                                .into_iter()
+                                .map(|e| {
+                                    let field = as_string(e.clone())?;
+                                    match field.as_str() {
+                                        "desc" => Ok(CopyField::Desc),
+                                        "labels" => Ok(CopyField::Labels),
+                                        "checklists" => Ok(CopyField::Checklists),
+                                        "attachments" => Ok(CopyField::Attachments),
+                                        "due" => Ok(CopyField::Due),
+                                        "members" => Ok(CopyField::Members),
+                                        "comments" => Ok(CopyField::Comments),
+                                        f => e.error(format!("the field '{}' cannot be kept", f)),
+                                    }
+                                })
+                                .collect::<Result<_, _>>()?;
+                        Ok(TaskBody::Action(ActionTask::CopyToColumn(to, keep)))
                     }
                     "move" => {
                         let to: Target = field_by_name("to", params).and_then(|y| y.try_into())?;
@@ -118,20 +423,86 @@ impl<'a> TryFrom<ParametrizedYaml<'a>> for TaskBody {
                             .map(|name| CardInfo { name })?;
                         Ok(TaskBody::Action(ActionTask::AddToColumn(name, to)))
                     }
-                    _ => error(action_type.as_str()),
+                    "labels" => {
+                        let op = field_by_name("op", params.clone()).and_then(as_string)?;
+                        let op = match op.as_str() {
+                            "add" => LabelOp::Add,
+                            "remove" => LabelOp::Remove,
+                            "toggle" => LabelOp::Toggle,
+                            "replace" => LabelOp::Replace,
+                            e => return error(&params, "op", e),
+                        };
+                        let labels = field_by_name("labels", params.clone())
+                            .and_then(elements)?
+                            .into_iter()
+                            .map(|e| match field_by_name("name", e.clone()) {
+                                Ok(name) => Ok(NewLabel {
+                                    name: as_string(name)?,
+                                    color: or_default(
+                                        field_by_name("color", e).and_then(as_string).map(Some),
+                                        None,
+                                    )?,
+                                }),
+                                Err(_) => as_string(e).map(|name| NewLabel { name, color: None }),
+                            })
+                            .collect::<Result<_, _>>()?;
+                        let create =
+                            or_default(field_by_name("create", params).and_then(as_bool), false)?;
+                        Ok(TaskBody::Action(ActionTask::Labels(LabelAction {
+                            op,
+                            labels,
+                            create,
+                        })))
+                    }
+                    "rewrite" => {
+                        let name = rewriting("name", params.clone())?;
+                        let desc = rewriting("desc", params.clone())?;
+                        if name.is_none() && desc.is_none() {
+                            return params
+                                .error("the rewrite action needs name or desc".to_string());
+                        }
+                        Ok(TaskBody::Action(ActionTask::Rewrite(RewriteAction {
+                            name,
+                            desc,
+                        })))
+                    }
+                    _ => error(&params, "type", action_type.as_str()),
                 }
             }
+            "filter" if field_by_name("expr", params.clone()).is_ok() => {
+                let expr = field_by_name("expr", params).and_then(|y| y.try_into())?;
+                Ok(TaskBody::Filter(FilterTask::Expr(expr)))
+            }
             "filter" => {
                 let by = or_default(
                     field_by_name("by", params.clone()).and_then(as_string),
                     "name".to_string(),
                 )?;
-                let rhs = field_by_name("rhs", params.clone()).and_then(as_string)?;
-                let case = or_default(field_by_name("case", params).and_then(as_bool), true)?;
+                let rhs = || field_by_name("rhs", params.clone()).and_then(as_string);
+                let case = or_default(
+                    field_by_name("case", params.clone()).and_then(as_bool),
+                    true,
+                )?;
                 match by.as_str() {
-                    "name" => Ok(TaskBody::Filter(FilterTask::Name(rhs.to_string(), case))),
-                    "label" => Ok(TaskBody::Filter(FilterTask::Label(rhs.to_string(), case))),
-                    _ => error("the field is not either name or label "),
+                    "name" => Ok(TaskBody::Filter(FilterTask::Name(text(
+                        rhs()?,
+                        params.clone(),
+                        case,
+                    )?))),
+                    "desc" => Ok(TaskBody::Filter(FilterTask::Desc(text(
+                        rhs()?,
+                        params.clone(),
+                        case,
+                    )?))),
+                    "label" => Ok(TaskBody::Filter(FilterTask::Label(rhs()?, case))),
+                    "labels" => Ok(TaskBody::Filter(FilterTask::Labels(LabelFilter {
+                        any_of: label_refs("any_of", params.clone())?,
+                        all_of: label_refs("all_of", params.clone())?,
+                        none_of: label_refs("none_of", params.clone())?,
+                        case,
+                    }))),
+                    "no_labels" => Ok(TaskBody::Filter(FilterTask::NoLabels)),
+                    e => error(&params, "by", e),
                 }
             }
             "order" => {
@@ -139,23 +510,73 @@ impl<'a> TryFrom<ParametrizedYaml<'a>> for TaskBody {
                     field_by_name("from", params.clone()).and_then(|from| from.try_into()),
                     Source::Pipe,
                 )?;
-                match tpe(params)?.as_str() {
+                match tpe(params.clone())?.as_str() {
                     "shuffle" => Ok(TaskBody::Order(OrderTask::Shuffle(from))),
-                    "sort" => Ok(TaskBody::Order(OrderTask::Sort(from))),
+                    "sort" => {
+                        let keys = or_default(
+                            field_by_name("by", params.clone()).and_then(elements),
+                            vec![],
+                        )?;
+                        let keys = keys
+                            .into_iter()
+                            .map(SortKey::try_from)
+                            .collect::<Result<_, _>>()?;
+                        Ok(TaskBody::Order(OrderTask::Sort(from, keys)))
+                    }
                     "reverse" => Ok(TaskBody::Order(OrderTask::Reverse(from))),
-                    t => error(t),
+                    t => error(&params, "type", t),
                 }
             }
+            "condition" => {
+                let predicate: Predicate =
+                    field_by_name("if", params.clone()).and_then(|y| y.try_into())?;
+                let then = field_by_name("then", params.clone()).and_then(as_string)?;
+                let otherwise = or_default(
+                    field_by_name("else", params).and_then(as_string).map(Some),
+                    None,
+                )?;
+                Ok(TaskBody::Condition(ConditionTask {
+                    predicate,
+                    then,
+                    otherwise,
+                }))
+            }
+            "foreach" => {
+                let task = field_by_name("task", params.clone()).and_then(as_string)?;
+                let on_error = or_default(
+                    field_by_name("on_error", params.clone()).and_then(as_string),
+                    "stop".to_string(),
+                )?;
+                let on_error = match on_error.as_str() {
+                    "stop" => ErrorPolicy::Stop,
+                    "continue" => ErrorPolicy::Continue,
+                    e => return error(&params, "on_error", e),
+                };
+                Ok(TaskBody::ForEach(ForEachTask { task, on_error }))
+            }
             "take" => {
                 let src: Source = or_default(
                     field_by_name("from", params.clone()).and_then(|from| from.try_into()),
                     Source::Pipe,
                 )?;
                 let place = or_default(params.clone().try_into(), Place::Top)?;
-                let size = or_default(field_by_name("size", params).and_then(as_i64), 0)? as usize;
-                Ok(TaskBody::Take(TakeTask { src, size, place }))
+                let size =
+                    or_default(field_by_name("size", params.clone()).and_then(as_i64), 0)? as usize;
+                let skip = or_default(field_by_name("skip", params.clone()).and_then(as_i64), 0)?;
+                if skip < 0 {
+                    return Err(FlowError::FieldError(FieldError::new(
+                        &params.field_path("skip"),
+                        format!("the skip {} should not be negative", skip),
+                    )));
+                }
+                Ok(TaskBody::Take(TakeTask {
+                    src,
+                    size,
+                    skip: skip as usize,
+                    place,
+                }))
             }
-            _ => error(task_type.as_str()),
+            _ => error(&value, "type", task_type.as_str()),
         }
     }
 }
@@ -173,16 +594,15 @@ pub fn field_by_name<'a>(
 ) -> Result<ParametrizedYaml<'a>, FlowError> {
     if let Yaml::Hash(h) = yml.yaml {
         h.get(&Yaml::String(name.to_string()))
-            .map(|y| ParametrizedYaml::new(y, yml.arguments))
-            .ok_or(FlowError::NoFieldError(format!(
-                "{} is absent for {:?}",
-                name, yml.yaml
-            )))
+            .map(|y| ParametrizedYaml::new(y, yml.arguments.clone()).at(&yml.field_path(name)))
+            .ok_or_else(|| {
+                FlowError::NoFieldError(FieldError::new(
+                    &yml.field_path(name),
+                    "the field is absent".to_string(),
+                ))
+            })
     } else {
-        Err(FlowError::SerdeError(format!(
-            "error while deserializing for {:?}",
-            yml.yaml
-        )))
+        yml.error(format!("should be a map with the field {}", name))
     }
 }
 
@@ -214,10 +634,7 @@ pub fn as_string<'a>(f: ParametrizedYaml<'a>) -> Result<String, FlowError> {
         |y| {
             y.as_str()
                 .map(ToString::to_string)
-                .ok_or(FlowError::SerdeError(format!(
-                    "type should be string but got :{:?}",
-                    f.yaml
-                )))
+                .map_or_else(|| f.error("should be a string".to_string()), Ok)
         },
     )
 }
@@ -227,11 +644,11 @@ pub fn as_bool<'a>(f: ParametrizedYaml<'a>) -> Result<bool, FlowError> {
         f.clone(),
         |s| {
             s.parse::<bool>()
-                .map_err(|s| FlowError::SerdeError(s.to_string()))
+                .or_else(|_| f.error(format!("should be a bool but got '{}'", s)))
         },
         |y| {
             y.as_bool()
-                .ok_or(FlowError::SerdeError("should be bool".to_string()))
+                .map_or_else(|| f.error("should be a bool".to_string()), Ok)
         },
     )
 }
@@ -240,29 +657,31 @@ pub fn as_i64<'a>(f: ParametrizedYaml<'a>) -> Result<i64, FlowError> {
         f.clone(),
         |s| {
             s.parse::<i64>()
-                .map_err(|s| FlowError::SerdeError(s.to_string()))
+                .or_else(|_| f.error(format!("should be a number but got '{}'", s)))
         },
         |y| {
             y.as_i64()
-                .ok_or(FlowError::SerdeError("size should be a number".to_string()))
+                .map_or_else(|| f.error("should be a number".to_string()), Ok)
         },
     )
 }
 
 pub fn as_vec_of_str<'a>(f: ParametrizedYaml<'a>) -> Result<Vec<String>, FlowError> {
+    elements(f)?.into_iter().map(as_string).collect()
+}
+
+/// The elements of a list with their paths like `task.params[1]`.
+pub fn elements<'a>(f: ParametrizedYaml<'a>) -> Result<Vec<ParametrizedYaml<'a>>, FlowError> {
     if let Some(elems) = f.yaml.as_vec() {
-        let mut res = vec![];
-        for e in elems.into_iter() {
-            res.push(
-                as_string(ParametrizedYaml::new(e, f.arguments.clone())).map(|s| s.to_string())?,
-            )
-        }
-        Ok(res)
+        Ok(elems
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                ParametrizedYaml::new(e, f.arguments.clone()).at(&format!("{}[{}]", f.path, i))
+            })
+            .collect())
     } else {
-        Err(FlowError::SerdeError(format!(
-            "type should be a vec but got :{:?}",
-            f.yaml
-        )))
+        f.error("should be a list".to_string())
     }
 }
 
@@ -272,10 +691,7 @@ mod tests {
 
     use yaml_rust::{Yaml, YamlLoader};
 
-    use crate::{
-        files::yml_str_to,
-        task::{self, tasks::*},
-    };
+    use crate::{err::FlowError, task::tasks::*};
 
     use super::ParametrizedYaml;
 
@@ -313,6 +729,7 @@ mod tests {
             TaskBody::Action(ActionTask::MoveToColumn(Target {
                 column: "repeat".to_string(),
                 place: Place::Top,
+                board: None,
             })),
         );
         success(
@@ -329,9 +746,178 @@ mod tests {
             TaskBody::Action(ActionTask::MoveToColumn(Target {
                 column: "repeat".to_string(),
                 place: Place::Top,
+                board: None,
+            })),
+        );
+        success(
+            (&yaml(
+                r#"
+        type: action
+        params:
+            type: move
+            to:
+                board: ENG
+                column: Archive
+                place: bottom
+        "#,
+            ))
+                .into(),
+            TaskBody::Action(ActionTask::MoveToColumn(Target {
+                column: "Archive".to_string(),
+                place: Place::Bottom,
+                board: Some("ENG".to_string()),
+            })),
+        );
+    }
+    #[test]
+    fn refresh() {
+        success(
+            (&yaml(
+                r#"
+        type: action
+        params:
+            type: refresh
+        "#,
+            ))
+                .into(),
+            TaskBody::Action(ActionTask::Refresh),
+        );
+    }
+    #[test]
+    fn delete() {
+        success(
+            (&yaml(
+                r#"
+        type: action
+        params:
+            type: delete
+            confirm: true
+        "#,
+            ))
+                .into(),
+            TaskBody::Action(ActionTask::Delete),
+        );
+        let unconfirmed: Result<TaskBody, _> = ParametrizedYaml::from(&yaml(
+            r#"
+        type: action
+        params:
+            type: delete
+        "#,
+        ))
+        .try_into();
+        assert!(unconfirmed.is_err());
+    }
+    #[test]
+    fn label_action() {
+        success(
+            (&yaml(
+                r#"
+        type: action
+        params:
+            type: labels
+            op: toggle
+            labels:
+                - Demand
+                - name: Fresh
+                  color: blue
+            create: true
+        "#,
+            ))
+                .into(),
+            TaskBody::Action(ActionTask::Labels(LabelAction {
+                op: LabelOp::Toggle,
+                labels: vec![
+                    NewLabel {
+                        name: "Demand".to_string(),
+                        color: None,
+                    },
+                    NewLabel {
+                        name: "Fresh".to_string(),
+                        color: Some("blue".to_string()),
+                    },
+                ],
+                create: true,
             })),
         );
     }
+
+    #[test]
+    fn rewrite() {
+        let rewrite: TaskBody = ParametrizedYaml::from(&yaml(
+            r#"
+        type: action
+        params:
+            type: rewrite
+            name: "{{name}} ✓"
+            desc:
+                find: "(\\d+)"
+                replace: "$1 on {{date}}"
+        "#,
+        ))
+        .try_into()
+        .unwrap();
+        match rewrite {
+            TaskBody::Action(ActionTask::Rewrite(RewriteAction {
+                name: Some(Rewriting::Template(name)),
+                desc: Some(Rewriting::Replace(find, replace)),
+            })) => {
+                assert_eq!(name.0, "{{name}} ✓");
+                assert_eq!(find.as_str(), r"(\d+)");
+                assert_eq!(replace.0, "$1 on {{date}}");
+            }
+            r => panic!("expected a rewrite, got {:?}", r),
+        }
+        for params in [
+            "name: \"{{title}}\"",
+            "desc: {find: \"(\", replace: x}",
+            "op: add",
+        ] {
+            let yml = format!("type: action\nparams:\n    type: rewrite\n    {}", params);
+            let res: Result<TaskBody, _> = ParametrizedYaml::from(&yaml(&yml)).try_into();
+            assert!(res.is_err(), "{}", params);
+        }
+    }
+
+    #[test]
+    fn copy_keep() {
+        success(
+            (&yaml(
+                r#"
+        type: action
+        params:
+            type: copy
+            to:
+                column: Repeating
+            keep:
+                - desc
+                - checklists
+        "#,
+            ))
+                .into(),
+            TaskBody::Action(ActionTask::CopyToColumn(
+                Target {
+                    column: "Repeating".to_string(),
+                    place: Place::Top,
+                    board: None,
+                },
+                vec![CopyField::Desc, CopyField::Checklists],
+            )),
+        );
+        let unknown: Result<TaskBody, _> = ParametrizedYaml::from(&yaml(
+            r#"
+        type: action
+        params:
+            type: copy
+            to:
+                column: Repeating
+            keep:
+                - stickers
+        "#,
+        ))
+        .try_into();
+        assert!(unknown.is_err());
+    }
+
     #[test]
     fn take() {
         success(
@@ -346,13 +932,49 @@ mod tests {
             ))
                 .into(),
             TaskBody::Take(TakeTask {
-                src: Source::Board,
+                src: Source::Board("ENG".to_string()),
                 size: 0,
+                skip: 0,
                 place: Place::Top,
             }),
         );
     }
 
+    #[test]
+    fn take_skip() {
+        success(
+            (&yaml(
+                r#"
+            type: take
+            params: 
+                from:
+                    type: column
+                    source: Archive
+                size: 10
+                skip: 10
+                place: bottom
+        "#,
+            ))
+                .into(),
+            TaskBody::Take(TakeTask {
+                src: Source::Column("Archive".to_string()),
+                size: 10,
+                skip: 10,
+                place: Place::Bottom,
+            }),
+        );
+        let negative: Result<TaskBody, _> = ParametrizedYaml::from(&yaml(
+            r#"
+        type: take
+        params:
+            size: 10
+            skip: -1
+        "#,
+        ))
+        .try_into();
+        assert!(matches!(negative, Err(FlowError::FieldError(_))));
+    }
+
     #[test]
     fn flow() {
         success(
@@ -378,6 +1000,298 @@ mod tests {
         );
     }
 
+    #[test]
+    fn condition() {
+        success(
+            (&yaml(
+                r#"
+        type: condition
+        params:
+            if:
+                type: size
+                op: ge
+                value: 5
+            then: _move_to_repeat
+            else: _print
+        "#,
+            ))
+                .into(),
+            TaskBody::Condition(ConditionTask {
+                predicate: Predicate::Size(Comparison::Ge, 5),
+                then: "_move_to_repeat".to_string(),
+                otherwise: Some("_print".to_string()),
+            }),
+        );
+        success(
+            (&yaml(
+                r#"
+        type: condition
+        params:
+            if:
+                type: any
+                filter: _filter_demand
+            then: _move_to_repeat
+        "#,
+            ))
+                .into(),
+            TaskBody::Condition(ConditionTask {
+                predicate: Predicate::Any(Check::Filter("_filter_demand".to_string())),
+                then: "_move_to_repeat".to_string(),
+                otherwise: None,
+            }),
+        );
+        success(
+            (&yaml(
+                r#"
+        type: condition
+        params:
+            if:
+                type: all
+                expr:
+                    label: Demand
+            then: _move_to_repeat
+        "#,
+            ))
+                .into(),
+            TaskBody::Condition(ConditionTask {
+                predicate: Predicate::All(Check::Expr(Expr::Label("Demand".to_string(), false))),
+                then: "_move_to_repeat".to_string(),
+                otherwise: None,
+            }),
+        );
+        let both: Result<TaskBody, _> = ParametrizedYaml::from(&yaml(
+            r#"
+        type: condition
+        params:
+            if:
+                type: any
+                filter: _filter_demand
+                expr:
+                    label: Demand
+            then: _move_to_repeat
+        "#,
+        ))
+        .try_into();
+        assert!(both.is_err());
+    }
+
+    #[test]
+    fn foreach() {
+        success(
+            (&yaml(
+                r#"
+        type: foreach
+        params:
+            task: _rename
+            on_error: continue
+        "#,
+            ))
+                .into(),
+            TaskBody::ForEach(ForEachTask {
+                task: "_rename".to_string(),
+                on_error: ErrorPolicy::Continue,
+            }),
+        );
+        success(
+            (&yaml(
+                r#"
+        type: foreach
+        params:
+            task: _rename
+        "#,
+            ))
+                .into(),
+            TaskBody::ForEach(ForEachTask {
+                task: "_rename".to_string(),
+                on_error: ErrorPolicy::Stop,
+            }),
+        );
+    }
+
+    #[test]
+    fn filter_mode() {
+        success(
+            (&yaml(
+                r#"
+        type: filter
+        params:
+            by: desc
+            rhs: collaps
+            mode: starts_with
+            case: false
+        "#,
+            ))
+                .into(),
+            TaskBody::Filter(FilterTask::Desc(Text {
+                pattern: "collaps".to_string(),
+                mode: MatchMode::StartsWith,
+                case: false,
+            })),
+        );
+        let regex: Result<TaskBody, _> = ParametrizedYaml::from(&yaml(
+            r#"
+        type: filter
+        params:
+            rhs: "collaps("
+            mode: regex
+        "#,
+        ))
+        .try_into();
+        assert!(regex.is_err());
+        let negative: Result<TaskBody, _> = ParametrizedYaml::from(&yaml(
+            r#"
+        type: filter
+        params:
+            rhs: collapse
+            mode: fuzzy
+            distance: -1
+        "#,
+        ))
+        .try_into();
+        assert!(matches!(negative, Err(FlowError::FieldError(_))));
+    }
+
+    #[test]
+    fn labels() {
+        success(
+            (&yaml(
+                r#"
+        type: filter
+        params:
+            by: labels
+            any_of:
+                - Demand
+                - color: green
+            none_of:
+                - Mispronounced
+        "#,
+            ))
+                .into(),
+            TaskBody::Filter(FilterTask::Labels(LabelFilter {
+                any_of: vec![
+                    LabelRef::Name("Demand".to_string()),
+                    LabelRef::Color("green".to_string()),
+                ],
+                all_of: vec![],
+                none_of: vec![LabelRef::Name("Mispronounced".to_string())],
+                case: true,
+            })),
+        );
+        success(
+            (&yaml(
+                r#"
+        type: filter
+        params:
+            by: no_labels
+        "#,
+            ))
+                .into(),
+            TaskBody::Filter(FilterTask::NoLabels),
+        );
+    }
+
+    #[test]
+    fn expr() {
+        success(
+            (&yaml(
+                r#"
+        type: filter
+        params:
+            expr:
+                and:
+                    - label: Demand
+                    - not:
+                        label: Mispronounced
+                    - or:
+                        - name: Word
+                          case: true
+                          mode: contains
+                        - desc: word
+                          mode: fuzzy
+                          distance: 1
+                        - list: Archive
+                        - pos:
+                            op: lt
+                            value: 5
+        "#,
+            ))
+                .into(),
+            TaskBody::Filter(FilterTask::Expr(Expr::And(vec![
+                Expr::Label("Demand".to_string(), false),
+                Expr::Not(Box::new(Expr::Label("Mispronounced".to_string(), false))),
+                Expr::Or(vec![
+                    Expr::Name(Text {
+                        pattern: "Word".to_string(),
+                        mode: MatchMode::Contains,
+                        case: true,
+                    }),
+                    Expr::Desc(Text {
+                        pattern: "word".to_string(),
+                        mode: MatchMode::Fuzzy(1),
+                        case: false,
+                    }),
+                    Expr::List("Archive".to_string()),
+                    Expr::Pos(Comparison::Lt, 5),
+                ]),
+            ]))),
+        );
+        let two: Result<TaskBody, _> = ParametrizedYaml::from(&yaml(
+            r#"
+        type: filter
+        params:
+            expr:
+                name: word
+                label: Demand
+        "#,
+        ))
+        .try_into();
+        assert!(two
+            .unwrap_err()
+            .to_string()
+            .ends_with("should have only one of name, label, wrap them in and"));
+    }
+
+    #[test]
+    fn sort() {
+        success(
+            (&yaml(
+                r#"
+        type: order
+        params:
+            type: sort
+            by:
+                - due desc
+                - created
+        "#,
+            ))
+                .into(),
+            TaskBody::Order(OrderTask::Sort(
+                Source::Pipe,
+                vec![
+                    SortKey {
+                        field: SortField::Due,
+                        desc: true,
+                    },
+                    SortKey {
+                        field: SortField::Created,
+                        desc: false,
+                    },
+                ],
+            )),
+        );
+        let unknown: Result<TaskBody, _> = ParametrizedYaml::from(&yaml(
+            r#"
+        type: order
+        params:
+            type: sort
+            by:
+                - due down
+        "#,
+        ))
+        .try_into();
+        assert!(unknown.is_err());
+    }
+
     #[test]
     fn group() {
         success(
@@ -1,8 +1,15 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
 use crate::err::FlowError;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Task {
     pub name: String,
+    /// the board the columns and the labels of the task and its steps are on,
+    /// the board of the calling task or of the file by default
+    pub board: Option<String>,
     pub body: TaskBody,
 }
 #[derive(Debug, Clone, PartialEq)]
@@ -13,51 +20,79 @@ pub enum TaskBody {
     Action(ActionTask),
     Group(GroupTask),
     Flow(FlowTask),
+    Condition(ConditionTask),
+    ForEach(ForEachTask),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct TakeTask {
     pub src: Source,
     pub size: usize,
+    pub skip: usize,
     pub place: Place,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum Place {
+    #[default]
     Top,
     Bottom,
     Random,
 }
 
-impl Default for Place {
-    fn default() -> Self {
-        Place::Top
-    }
-}
 #[derive(Debug, Clone, PartialEq)]
 pub struct Target {
     pub column: String,
     pub place: Place,
+    /// the board of the column, the board of the task by default
+    pub board: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Source {
     Pipe,
-    Board,
+    /// the cards of the board by its name
+    Board(String),
     Column(String),
+    /// the archived cards of the board by its name
+    ArchivedBoard(String),
+    /// the archived cards of the column
+    ArchivedColumn(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum OrderTask {
     Shuffle(Source),
-    Sort(Source),
+    /// the keys are compared one by one, the name ascending if there are none
+    Sort(Source, Vec<SortKey>),
     Reverse(Source),
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub desc: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortField {
+    Pos,
+    Name,
+    /// the length of the description
+    DescLength,
+    /// the date of the last activity
+    Activity,
+    Due,
+    /// the creation time taken from the id
+    Created,
+    /// the number of the labels
+    Labels,
+}
+
 impl OrderTask {
     pub fn source(&self) -> Result<Source, FlowError> {
         Ok(match self {
-            OrderTask::Shuffle(s) | OrderTask::Sort(s) | OrderTask::Reverse(s) => s.clone(),
+            OrderTask::Shuffle(s) | OrderTask::Sort(s, _) | OrderTask::Reverse(s) => s.clone(),
         })
     }
 }
@@ -65,19 +100,285 @@ impl OrderTask {
 #[derive(Debug, Clone, PartialEq)]
 pub enum ActionTask {
     PrintToConsole,
-    CopyToColumn(Target),
+    Refresh,
+    /// copies the cards with the fields to keep, the copies go on in the pipe
+    CopyToColumn(Target, Vec<CopyField>),
     MoveToColumn(Target),
     AddToColumn(CardInfo, Target),
+    /// writes the positions of the cards so their columns follow the order of the pipe
+    ApplyOrder,
+    Archive,
+    /// puts the archived cards back into their columns
+    Unarchive,
+    Delete,
+    Labels(LabelAction),
+    /// changes the name and the description of every card of the pipe
+    Rewrite(RewriteAction),
+}
+/// A field of a card kept by the copy besides the name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyField {
+    Desc,
+    Labels,
+    Checklists,
+    Attachments,
+    Due,
+    Members,
+    Comments,
+}
+
+impl CopyField {
+    /// the name in the yaml and in trello's `keepFromSource`
+    pub fn name(&self) -> &'static str {
+        match self {
+            CopyField::Desc => "desc",
+            CopyField::Labels => "labels",
+            CopyField::Checklists => "checklists",
+            CopyField::Attachments => "attachments",
+            CopyField::Due => "due",
+            CopyField::Members => "members",
+            CopyField::Comments => "comments",
+        }
+    }
 }
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CardInfo {
     pub name: String,
 }
 
+/// The new name and description, the absent ones are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewriteAction {
+    pub name: Option<Rewriting>,
+    pub desc: Option<Rewriting>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Rewriting {
+    Template(Template),
+    /// replaces every match of the regex, the groups are taken by `$1` or `${name}`
+    Replace(Regex, Template),
+}
+
+impl PartialEq for Rewriting {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Rewriting::Template(lhs), Rewriting::Template(rhs)) => lhs == rhs,
+            (Rewriting::Replace(lr, lt), Rewriting::Replace(rr, rt)) => {
+                lr.as_str() == rr.as_str() && lt == rt
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Rewriting {
+    pub fn apply<F: Fn(&str) -> String>(&self, text: &str, value: F) -> String {
+        match self {
+            Rewriting::Template(template) => template.render(value),
+            Rewriting::Replace(regex, template) => {
+                // the values of the fields are taken literally, not as the groups
+                let replacement = template.render(|f| value(f).replace('$', "$$"));
+                regex.replace_all(text, replacement.as_str()).to_string()
+            }
+        }
+    }
+}
+
+/// The card fields a template can refer to as `{{field}}`.
+pub const TEMPLATE_FIELDS: [&str; 7] = ["name", "desc", "list", "url", "labels", "due", "date"];
+
+/// A text with `{{field}}` placeholders filled with the fields of a card.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template(pub String);
+
+impl Template {
+    fn placeholder() -> &'static Regex {
+        static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+        PLACEHOLDER.get_or_init(|| {
+            Regex::new(r"\{\{\s*(\w+)\s*\}\}").expect("the placeholder regex should be valid")
+        })
+    }
+
+    pub fn fields(&self) -> Vec<String> {
+        Template::placeholder()
+            .captures_iter(&self.0)
+            .map(|c| c[1].to_string())
+            .collect()
+    }
+
+    pub fn render<F: Fn(&str) -> String>(&self, value: F) -> String {
+        Template::placeholder()
+            .replace_all(&self.0, |c: &regex::Captures| value(&c[1]))
+            .to_string()
+    }
+}
+
+/// Changes the labels of every card of the pipe,
+/// `create` makes the missing labels with the given colours instead of failing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelAction {
+    pub op: LabelOp,
+    pub labels: Vec<NewLabel>,
+    pub create: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelOp {
+    Add,
+    Remove,
+    /// removes the labels a card has and adds the ones it has not
+    Toggle,
+    /// leaves the card only with the labels
+    Replace,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewLabel {
+    pub name: String,
+    /// the colour of the created label
+    pub color: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum FilterTask {
-    Name(String, bool),
+    Name(Text),
+    Desc(Text),
     Label(String, bool),
+    Labels(LabelFilter),
+    /// the cards without any label
+    NoLabels,
+    Expr(Expr),
+}
+
+/// A label given by its name or by its colour, a colour can stand for several labels.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelRef {
+    Name(String),
+    Color(String),
+}
+
+/// The cards having any of, all of and none of the labels, the empty lists are skipped.
+/// `case` ignores the case of the names like the one of `FilterTask::Label`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LabelFilter {
+    pub any_of: Vec<LabelRef>,
+    pub all_of: Vec<LabelRef>,
+    pub none_of: Vec<LabelRef>,
+    pub case: bool,
+}
+
+impl LabelFilter {
+    pub fn refs(&self) -> impl Iterator<Item = &LabelRef> {
+        self.any_of
+            .iter()
+            .chain(self.all_of.iter())
+            .chain(self.none_of.iter())
+    }
+}
+
+/// A pattern for a text of a card like the name or the description,
+/// `case` makes the comparison case-sensitive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Text {
+    pub pattern: String,
+    pub mode: MatchMode,
+    pub case: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum MatchMode {
+    #[default]
+    Exact,
+    Contains,
+    StartsWith,
+    /// the pattern compiled with the case of the text
+    Regex(Regex),
+    /// a part of the text differs from the pattern by at most the number of chars
+    Fuzzy(usize),
+}
+
+impl PartialEq for MatchMode {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (MatchMode::Regex(lhs), MatchMode::Regex(rhs)) => lhs.as_str() == rhs.as_str(),
+            (MatchMode::Fuzzy(lhs), MatchMode::Fuzzy(rhs)) => lhs == rhs,
+            (lhs, rhs) => std::mem::discriminant(lhs) == std::mem::discriminant(rhs),
+        }
+    }
+}
+
+impl Text {
+    pub fn test(&self, text: &str) -> bool {
+        if let MatchMode::Regex(regex) = &self.mode {
+            return regex.is_match(text);
+        }
+        let (text, pattern) = if self.case {
+            (text.to_string(), self.pattern.clone())
+        } else {
+            (text.to_lowercase(), self.pattern.to_lowercase())
+        };
+        match &self.mode {
+            MatchMode::Exact | MatchMode::Regex(_) => text == pattern,
+            MatchMode::Contains => text.contains(&pattern),
+            MatchMode::StartsWith => text.starts_with(&pattern),
+            MatchMode::Fuzzy(max) => edit_distance(&pattern, &text, true) <= *max,
+        }
+    }
+}
+
+/// The number of the inserted, deleted and substituted chars turning one string into another,
+/// with `substring` the least number turning `lhs` into any part of `rhs`.
+pub fn edit_distance(lhs: &str, rhs: &str, substring: bool) -> usize {
+    let rhs: Vec<char> = rhs.chars().collect();
+    // a part can start anywhere in rhs, so the first row is free
+    let mut prev: Vec<usize> = if substring {
+        vec![0; rhs.len() + 1]
+    } else {
+        (0..=rhs.len()).collect()
+    };
+    for (i, l) in lhs.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, r) in rhs.iter().enumerate() {
+            let sub = prev[j] + usize::from(l != *r);
+            cur.push(sub.min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    if substring {
+        prev.into_iter().min().unwrap_or_default()
+    } else {
+        prev[rhs.len()]
+    }
+}
+
+/// A predicate over a card, `case` makes the text comparisons case-sensitive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Name(Text),
+    Desc(Text),
+    Label(String, bool),
+    Color(String),
+    NoLabels,
+    /// the column of the card
+    List(String),
+    /// the index of the card in its column starting from 0
+    Pos(Comparison, usize),
+}
+
+impl Expr {
+    /// the predicates on the card without `and`, `or` and `not`
+    pub fn leaves(&self) -> Vec<&Expr> {
+        match self {
+            Expr::And(exprs) | Expr::Or(exprs) => exprs.iter().flat_map(Expr::leaves).collect(),
+            Expr::Not(expr) => expr.leaves(),
+            leaf => vec![leaf],
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -89,3 +390,124 @@ pub struct FlowTask {
 pub struct GroupTask {
     pub steps: Vec<String>,
 }
+
+/// Runs the task for every card of the pipe separately
+/// and puts the pipes the runs end with together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForEachTask {
+    pub task: String,
+    pub on_error: ErrorPolicy,
+}
+
+/// What happens to the rest of the cards when the task fails on one of them.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ErrorPolicy {
+    #[default]
+    Stop,
+    /// the failed card is skipped
+    Continue,
+}
+
+/// Runs `then` or `otherwise` with the pipe depending on the predicate,
+/// the pipe goes on unchanged if the predicate fails and there is no `otherwise`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionTask {
+    pub predicate: Predicate,
+    pub then: String,
+    pub otherwise: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Empty,
+    Size(Comparison, usize),
+    /// the filter keeps at least one card of the pipe
+    Any(Check),
+    /// the filter keeps all the cards of the pipe
+    All(Check),
+}
+
+/// The filter of a predicate, a filter task or an expression like the one of a filter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Check {
+    Filter(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Comparison {
+    pub fn test(&self, lhs: usize, rhs: usize) -> bool {
+        match self {
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::{Regex, RegexBuilder};
+
+    use super::{MatchMode, Rewriting, Template, Text};
+
+    fn text(pattern: &str, mode: MatchMode, case: bool) -> Text {
+        Text {
+            pattern: pattern.to_string(),
+            mode,
+            case,
+        }
+    }
+
+    #[test]
+    fn modes() {
+        let name = "collapse (v.) — to fall down";
+        assert!(!text("collaps", MatchMode::Exact, false).test(name));
+        assert!(text("COLLAPS", MatchMode::Contains, false).test(name));
+        assert!(!text("COLLAPS", MatchMode::Contains, true).test(name));
+        assert!(text("collapse (v.)", MatchMode::StartsWith, true).test(name));
+        assert!(!text("to fall", MatchMode::StartsWith, true).test(name));
+        assert!(text("collaps", MatchMode::Fuzzy(0), false).test(name));
+        assert!(text("colapse", MatchMode::Fuzzy(1), false).test(name));
+        assert!(!text("klapze", MatchMode::Fuzzy(1), false).test(name));
+
+        let regex = RegexBuilder::new(r"^COLL\w+ \(v\.\)")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        assert!(text("", MatchMode::Regex(regex), false).test(name));
+    }
+
+    #[test]
+    fn templates() {
+        let value = |f: &str| match f {
+            "name" => "collapse".to_string(),
+            "date" => "2026-10-18".to_string(),
+            _ => "$1".to_string(),
+        };
+        let template = Template("{{name}} ✓ {{ date }} {{x}}".to_string());
+        assert_eq!(template.fields(), vec!["name", "date", "x"]);
+        assert_eq!(template.render(value), "collapse ✓ 2026-10-18 $1");
+
+        let replace = Rewriting::Replace(
+            Regex::new(r"repeated (\d+)").unwrap(),
+            Template("seen $1 times {{date}} {{x}}".to_string()),
+        );
+        assert_eq!(
+            replace.apply("to fall, repeated 3", value),
+            "to fall, seen 3 times 2026-10-18 $1"
+        );
+    }
+}
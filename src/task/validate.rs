@@ -0,0 +1,690 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+};
+
+use crate::trello::{find_label, Board, Label, List};
+
+use super::{
+    context::TaskContext,
+    tasks::{
+        edit_distance, ActionTask, Check, ConditionTask, Expr, FilterTask, LabelRef, OrderTask,
+        Predicate, Source, TakeTask, Target, TaskBody,
+    },
+};
+
+/// A problem of the tasks found before running them.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Issue {
+    /// the task refers to a step that is not defined
+    UndefinedStep { task: String, step: String },
+    /// the tasks refer to each other in a loop, the first one is repeated at the end
+    Cycle(Vec<String>),
+    /// the task reads the pipe but is started without any step before it
+    NoUpstream(String),
+    /// the private task is not a step of any other task
+    Unreferenced(String),
+    /// the predicate of the condition runs a task that is not a filter and can change the board
+    NotAFilter { task: String, filter: String },
+    /// the board is not among the open ones, a close name is suggested if there is one
+    UnknownBoard {
+        task: String,
+        board: String,
+        suggestion: Option<String>,
+    },
+    /// the column is not on the board, a close name is suggested if there is one
+    UnknownColumn {
+        task: String,
+        column: String,
+        suggestion: Option<String>,
+    },
+    /// the label is not on the board, a close name is suggested if there is one
+    UnknownLabel {
+        task: String,
+        label: String,
+        suggestion: Option<String>,
+    },
+}
+
+impl Issue {
+    /// the errors stop the tasks from running, the rest are warnings
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Issue::Unreferenced(_))
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::UndefinedStep { task, step } => {
+                write!(f, "the task {} refers to the undefined step {}", task, step)
+            }
+            Issue::Cycle(tasks) => write!(f, "the tasks form a cycle: {}", tasks.join(" -> ")),
+            Issue::NoUpstream(task) => write!(
+                f,
+                "the task {} reads the pipe but nothing comes before it",
+                task
+            ),
+            Issue::Unreferenced(task) => {
+                write!(f, "the private task {} is not used by any task", task)
+            }
+            Issue::NotAFilter { task, filter } => write!(
+                f,
+                "the condition {} checks the pipe with {} that is not a filter",
+                task, filter
+            ),
+            Issue::UnknownBoard {
+                task,
+                board,
+                suggestion,
+            } => {
+                write!(f, "the task {} refers to the unknown board {}", task, board)?;
+                did_you_mean(f, suggestion)
+            }
+            Issue::UnknownColumn {
+                task,
+                column,
+                suggestion,
+            } => {
+                write!(
+                    f,
+                    "the task {} refers to the unknown column {}",
+                    task, column
+                )?;
+                did_you_mean(f, suggestion)
+            }
+            Issue::UnknownLabel {
+                task,
+                label,
+                suggestion,
+            } => {
+                write!(f, "the task {} refers to the unknown label {}", task, label)?;
+                did_you_mean(f, suggestion)
+            }
+        }
+    }
+}
+
+fn did_you_mean(f: &mut Formatter<'_>, suggestion: &Option<String>) -> fmt::Result {
+    match suggestion {
+        Some(s) => write!(f, ", did you mean {}?", s),
+        None => Ok(()),
+    }
+}
+
+fn steps(body: &TaskBody) -> Vec<&String> {
+    match body {
+        TaskBody::Flow(f) => f.steps.iter().collect(),
+        TaskBody::Group(g) => g.steps.iter().collect(),
+        TaskBody::Condition(c) => {
+            let mut steps = vec![&c.then];
+            steps.extend(c.otherwise.iter());
+            if let Predicate::Any(Check::Filter(t)) | Predicate::All(Check::Filter(t)) =
+                &c.predicate
+            {
+                steps.push(t);
+            }
+            steps
+        }
+        TaskBody::ForEach(f) => vec![&f.task],
+        _ => vec![],
+    }
+}
+
+/// The task expects cards from the step before it.
+/// A flow reads the pipe when its first step does, a group runs every step on its own.
+fn reads_pipe(ctx: &TaskContext, task: &str, visited: &mut HashSet<String>) -> bool {
+    if !visited.insert(task.to_string()) {
+        return false;
+    }
+    match ctx.tasks.get(task).map(|t| &t.body) {
+        Some(TaskBody::Take(TakeTask { src, .. })) => *src == Source::Pipe,
+        Some(TaskBody::Order(o)) => matches!(
+            o,
+            OrderTask::Shuffle(Source::Pipe)
+                | OrderTask::Sort(Source::Pipe, _)
+                | OrderTask::Reverse(Source::Pipe)
+        ),
+        Some(TaskBody::Filter(_)) | Some(TaskBody::Condition(_)) | Some(TaskBody::ForEach(_)) => {
+            true
+        }
+        Some(TaskBody::Action(a)) => matches!(
+            a,
+            ActionTask::PrintToConsole
+                | ActionTask::CopyToColumn(..)
+                | ActionTask::MoveToColumn(_)
+                | ActionTask::ApplyOrder
+                | ActionTask::Archive
+                | ActionTask::Unarchive
+                | ActionTask::Delete
+                | ActionTask::Labels(_)
+                | ActionTask::Rewrite(_)
+        ),
+        Some(TaskBody::Flow(f)) => f
+            .steps
+            .first()
+            .map(|s| reads_pipe(ctx, s, visited))
+            .unwrap_or(false),
+        Some(TaskBody::Group(_)) | None => false,
+    }
+}
+
+fn cycles(
+    ctx: &TaskContext,
+    task: &str,
+    path: &mut Vec<String>,
+    done: &mut HashSet<String>,
+    issues: &mut Vec<Issue>,
+) {
+    if let Some(idx) = path.iter().position(|t| t == task) {
+        let mut cycle = path[idx..].to_vec();
+        cycle.push(task.to_string());
+        issues.push(Issue::Cycle(cycle));
+        return;
+    }
+    if done.contains(task) {
+        return;
+    }
+    if let Some(t) = ctx.tasks.get(task) {
+        path.push(task.to_string());
+        for step in steps(&t.body) {
+            cycles(ctx, step, path, done, issues);
+        }
+        path.pop();
+    }
+    done.insert(task.to_string());
+}
+
+/// Checks the steps of the tasks without calling trello:
+/// every step is defined, the tasks do not refer to each other in a loop,
+/// the tasks started without a step before them do not read the pipe
+/// and every private task (starting with `_`) is used somewhere.
+pub fn validate(ctx: &TaskContext) -> Vec<Issue> {
+    let mut issues = vec![];
+    let mut names: Vec<&String> = ctx.tasks.keys().collect();
+    names.sort();
+
+    let mut referenced = HashSet::new();
+    let mut in_flows = HashSet::new();
+    let mut started = vec![];
+    for name in names.iter() {
+        let body = &ctx.tasks[*name].body;
+        for step in steps(body) {
+            referenced.insert(step);
+            if !ctx.tasks.contains_key(step) {
+                issues.push(Issue::UndefinedStep {
+                    task: name.to_string(),
+                    step: step.clone(),
+                });
+            }
+        }
+        if let TaskBody::Condition(c) = body {
+            if let Predicate::Any(Check::Filter(t)) | Predicate::All(Check::Filter(t)) =
+                &c.predicate
+            {
+                if ctx
+                    .tasks
+                    .get(t)
+                    .is_some_and(|t| !matches!(t.body, TaskBody::Filter(_)))
+                {
+                    issues.push(Issue::NotAFilter {
+                        task: name.to_string(),
+                        filter: t.clone(),
+                    });
+                }
+            }
+        }
+        match body {
+            TaskBody::Group(g) => started.extend(g.steps.iter()),
+            TaskBody::Flow(f) => in_flows.extend(f.steps.iter()),
+            // the branches and the predicates of a condition get its pipe
+            TaskBody::Condition(_) | TaskBody::ForEach(_) => in_flows.extend(steps(body)),
+            _ => {}
+        }
+    }
+    // the steps of a group are run on their own and the public tasks are started by the user,
+    // unless they are the parts of flows
+    started.extend(
+        names
+            .iter()
+            .filter(|n| !n.starts_with('_') && !in_flows.contains(*n)),
+    );
+
+    let mut done = HashSet::new();
+    for name in names.iter() {
+        cycles(ctx, name, &mut vec![], &mut done, &mut issues);
+    }
+
+    for name in started {
+        if reads_pipe(ctx, name, &mut HashSet::new()) {
+            issues.push(Issue::NoUpstream(name.clone()));
+        }
+    }
+
+    for name in names {
+        if name.starts_with('_') && !referenced.contains(name) {
+            issues.push(Issue::Unreferenced(name.clone()));
+        }
+    }
+
+    issues.sort();
+    issues.dedup();
+    issues
+}
+
+/// The errors of the task and of all its steps, the task is started on its own.
+pub fn validate_task(ctx: &TaskContext, task: &str) -> Vec<Issue> {
+    let mut reachable = HashSet::new();
+    let mut queue = vec![task];
+    while let Some(t) = queue.pop() {
+        if reachable.insert(t) {
+            if let Some(t) = ctx.tasks.get(t) {
+                queue.extend(steps(&t.body).into_iter().map(String::as_str));
+            }
+        }
+    }
+
+    let mut issues: Vec<Issue> = validate(ctx)
+        .into_iter()
+        .filter(|i| match i {
+            Issue::UndefinedStep { task, .. }
+            | Issue::NoUpstream(task)
+            | Issue::UnknownBoard { task, .. }
+            | Issue::UnknownColumn { task, .. }
+            | Issue::UnknownLabel { task, .. }
+            | Issue::NotAFilter { task, .. } => reachable.contains(task.as_str()),
+            Issue::Cycle(tasks) => tasks.iter().any(|t| reachable.contains(t.as_str())),
+            Issue::Unreferenced(_) => false,
+        })
+        .collect();
+    let started = Issue::NoUpstream(task.to_string());
+    if !issues.contains(&started) && reads_pipe(ctx, task, &mut HashSet::new()) {
+        issues.push(started);
+    }
+    issues
+}
+
+/// The closest name ignoring the case, a third of the chars can differ.
+fn suggest<'a, I>(name: &str, names: I) -> Option<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let lower = name.to_lowercase();
+    names
+        .into_iter()
+        .map(|n| (edit_distance(&lower, &n.to_lowercase(), false), n))
+        .filter(|(d, _)| *d <= (name.chars().count() / 3).max(1))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, n)| n.to_string())
+}
+
+/// The tasks with the boards they run on, take the cards from and move or copy them to.
+fn board_refs(ctx: &TaskContext) -> Vec<(&str, &str)> {
+    let mut refs = vec![];
+    for (name, task) in ctx.tasks.iter() {
+        let board = match &task.body {
+            TaskBody::Action(
+                ActionTask::CopyToColumn(Target { board, .. }, _)
+                | ActionTask::MoveToColumn(Target { board, .. })
+                | ActionTask::AddToColumn(_, Target { board, .. }),
+            ) => board.as_deref(),
+            TaskBody::Take(TakeTask {
+                src: Source::Board(board) | Source::ArchivedBoard(board),
+                ..
+            })
+            | TaskBody::Order(
+                OrderTask::Shuffle(Source::Board(board) | Source::ArchivedBoard(board))
+                | OrderTask::Sort(Source::Board(board) | Source::ArchivedBoard(board), _)
+                | OrderTask::Reverse(Source::Board(board) | Source::ArchivedBoard(board)),
+            ) => Some(board.as_str()),
+            _ => None,
+        };
+        for board in [task.board.as_deref(), board].into_iter().flatten() {
+            refs.push((name.as_str(), board));
+        }
+    }
+    refs
+}
+
+/// The boards the tasks use, the board of the file goes first.
+pub fn boards(ctx: &TaskContext) -> Vec<&str> {
+    let mut boards = vec![ctx.board.as_str()];
+    for (_, board) in board_refs(ctx) {
+        if !boards.contains(&board) {
+            boards.push(board);
+        }
+    }
+    boards
+}
+
+/// Checks the boards the tasks refer to against the open ones.
+pub fn validate_boards(ctx: &TaskContext, open: &[Board]) -> Vec<Issue> {
+    let mut issues: Vec<Issue> = board_refs(ctx)
+        .into_iter()
+        .filter(|(_, board)| !open.iter().any(|b| b.name == *board))
+        .map(|(task, board)| Issue::UnknownBoard {
+            task: task.to_string(),
+            board: board.to_string(),
+            suggestion: suggest(board, open.iter().map(|b| b.name.as_str())),
+        })
+        .collect();
+    issues.sort();
+    issues
+}
+
+/// The boards every task can run on: its own board if it names one,
+/// otherwise the boards of the tasks running it and the board of the file when it is started directly.
+fn task_boards(ctx: &TaskContext) -> HashMap<&str, HashSet<&str>> {
+    let referenced: HashSet<&String> = ctx.tasks.values().flat_map(|t| steps(&t.body)).collect();
+    let mut boards: HashMap<&str, HashSet<&str>> = ctx
+        .tasks
+        .iter()
+        .map(|(name, task)| {
+            let boards = match &task.board {
+                Some(board) => HashSet::from([board.as_str()]),
+                None if !name.starts_with('_') || !referenced.contains(name) => {
+                    HashSet::from([ctx.board.as_str()])
+                }
+                None => HashSet::new(),
+            };
+            (name.as_str(), boards)
+        })
+        .collect();
+    // the boards go down the steps until nothing changes
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (name, task) in ctx.tasks.iter() {
+            let from = boards[name.as_str()].clone();
+            for step in steps(&task.body) {
+                if ctx.tasks.get(step).is_some_and(|s| s.board.is_none()) {
+                    let to = boards.entry(step.as_str()).or_default();
+                    for board in from.iter() {
+                        changed |= to.insert(board);
+                    }
+                }
+            }
+        }
+    }
+    boards
+}
+
+/// Checks the columns and the labels the tasks refer to against the ones of the board,
+/// only the tasks and the targets on the board are checked.
+pub fn validate_board(
+    ctx: &TaskContext,
+    board: &str,
+    lists: &[List],
+    labels: &[Label],
+) -> Vec<Issue> {
+    let mut issues = vec![];
+    let task_boards = task_boards(ctx);
+    for (name, task) in ctx.tasks.iter() {
+        let on_board = task_boards[name.as_str()].contains(board);
+        // the labels go with the flag of the case-insensitive comparison
+        let (columns, task_labels) = match &task.body {
+            TaskBody::Action(
+                ActionTask::CopyToColumn(
+                    Target {
+                        column, board: to, ..
+                    },
+                    _,
+                )
+                | ActionTask::MoveToColumn(Target {
+                    column, board: to, ..
+                })
+                | ActionTask::AddToColumn(
+                    _,
+                    Target {
+                        column, board: to, ..
+                    },
+                ),
+            ) => match to {
+                // the target on another board is checked with that board
+                Some(b) if b == board => (vec![column], vec![]),
+                Some(_) => (vec![], vec![]),
+                None if on_board => (vec![column], vec![]),
+                None => (vec![], vec![]),
+            },
+            _ if !on_board => (vec![], vec![]),
+            TaskBody::Take(TakeTask {
+                src: Source::Column(c) | Source::ArchivedColumn(c),
+                ..
+            }) => (vec![c], vec![]),
+            TaskBody::Order(
+                OrderTask::Shuffle(Source::Column(c) | Source::ArchivedColumn(c))
+                | OrderTask::Sort(Source::Column(c) | Source::ArchivedColumn(c), _)
+                | OrderTask::Reverse(Source::Column(c) | Source::ArchivedColumn(c)),
+            ) => (vec![c], vec![]),
+            TaskBody::Action(ActionTask::Labels(action)) if !action.create => (
+                vec![],
+                action.labels.iter().map(|l| (&l.name, false)).collect(),
+            ),
+            TaskBody::Filter(FilterTask::Label(label, case)) => (vec![], vec![(label, *case)]),
+            TaskBody::Filter(FilterTask::Labels(filter)) => (
+                vec![],
+                filter
+                    .refs()
+                    .filter_map(|r| match r {
+                        LabelRef::Name(name) => Some((name, filter.case)),
+                        LabelRef::Color(_) => None,
+                    })
+                    .collect(),
+            ),
+            TaskBody::Filter(FilterTask::Expr(expr))
+            | TaskBody::Condition(ConditionTask {
+                predicate: Predicate::Any(Check::Expr(expr)) | Predicate::All(Check::Expr(expr)),
+                ..
+            }) => {
+                let mut columns = vec![];
+                let mut labels = vec![];
+                for leaf in expr.leaves() {
+                    match leaf {
+                        Expr::List(c) => columns.push(c),
+                        Expr::Label(l, case) => labels.push((l, !*case)),
+                        _ => {}
+                    }
+                }
+                (columns, labels)
+            }
+            _ => (vec![], vec![]),
+        };
+        for column in columns {
+            if !lists.iter().any(|l| &l.name == column) {
+                issues.push(Issue::UnknownColumn {
+                    task: name.clone(),
+                    column: column.clone(),
+                    suggestion: suggest(column, lists.iter().map(|l| l.name.as_str())),
+                });
+            }
+        }
+        for (label, case) in task_labels {
+            if find_label(labels, label, case).is_none() {
+                issues.push(Issue::UnknownLabel {
+                    task: name.clone(),
+                    label: label.clone(),
+                    suggestion: suggest(label, labels.iter().map(|l| l.name.as_str())),
+                });
+            }
+        }
+    }
+    issues.sort();
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{files::read_file_into_string, task::context::from_str};
+
+    use super::{suggest, validate, validate_task, Issue};
+    use crate::task::tasks::edit_distance;
+
+    fn issues(yml: &str) -> Vec<Issue> {
+        validate(&from_str(yml, HashMap::new()).unwrap())
+    }
+
+    #[test]
+    fn examples() {
+        let yml = read_file_into_string("examples/task.yml").unwrap();
+        assert_eq!(issues(&yml), vec![]);
+    }
+
+    #[test]
+    fn problems() {
+        let yml = r#"
+board: ENG
+_take:
+  type: take
+  params:
+    from:
+      type: column
+      source: Archive
+_filter:
+  type: filter
+  params:
+    rhs: word
+_unused:
+  type: action
+  params:
+    type: print
+typo:
+  type: flow
+  params:
+    - _take
+    - _filetr
+no_upstream:
+  type: flow
+  params:
+    - _filter
+group:
+  type: group
+  params:
+    - _filter
+"#;
+        assert_eq!(
+            issues(yml),
+            vec![
+                Issue::UndefinedStep {
+                    task: "typo".to_string(),
+                    step: "_filetr".to_string()
+                },
+                Issue::NoUpstream("_filter".to_string()),
+                Issue::NoUpstream("no_upstream".to_string()),
+                Issue::Unreferenced("_unused".to_string()),
+            ]
+        );
+        assert!(!Issue::Unreferenced("_unused".to_string()).is_error());
+
+        let ctx = from_str(yml, HashMap::new()).unwrap();
+        assert_eq!(
+            validate_task(&ctx, "group"),
+            vec![Issue::NoUpstream("_filter".to_string())]
+        );
+        assert_eq!(validate_task(&ctx, "_take"), vec![]);
+        assert_eq!(
+            validate_task(&ctx, "_filter"),
+            vec![Issue::NoUpstream("_filter".to_string())]
+        );
+    }
+
+    #[test]
+    fn condition() {
+        let yml = r#"
+board: ENG
+_filter:
+  type: filter
+  params:
+    rhs: word
+_print:
+  type: action
+  params:
+    type: print
+_check:
+  type: condition
+  params:
+    if:
+      type: all
+      filter: _filter
+    then: _print
+    else: _prnt
+_check_print:
+  type: condition
+  params:
+    if:
+      type: any
+      filter: _print
+    then: _print
+started:
+  type: group
+  params:
+    - _check
+    - _check_print
+"#;
+        assert_eq!(
+            issues(yml),
+            vec![
+                Issue::UndefinedStep {
+                    task: "_check".to_string(),
+                    step: "_prnt".to_string()
+                },
+                Issue::NoUpstream("_check".to_string()),
+                Issue::NoUpstream("_check_print".to_string()),
+                Issue::NotAFilter {
+                    task: "_check_print".to_string(),
+                    filter: "_print".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let yml = r#"
+board: ENG
+a:
+  type: group
+  params:
+    - b
+b:
+  type: flow
+  params:
+    - c
+c:
+  type: group
+  params:
+    - a
+self:
+  type: group
+  params:
+    - self
+"#;
+        assert_eq!(
+            issues(yml),
+            vec![
+                Issue::Cycle(vec![
+                    "a".to_string(),
+                    "b".to_string(),
+                    "c".to_string(),
+                    "a".to_string()
+                ]),
+                Issue::Cycle(vec!["self".to_string(), "self".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(edit_distance("Repeating", "Repeatng", false), 1);
+        assert_eq!(edit_distance("", "abc", false), 3);
+        assert_eq!(edit_distance("kitten", "sitting", false), 3);
+        assert_eq!(edit_distance("kiten", "the kitten sat", true), 1);
+        assert_eq!(edit_distance("kitten", "the kitten sat", false), 8);
+
+        let lists = ["Later", "Repeating", "Idioms", "Archive"];
+        assert_eq!(suggest("Repeatng", lists), Some("Repeating".to_string()));
+        assert_eq!(suggest("archive", lists), Some("Archive".to_string()));
+        assert_eq!(suggest("Done", lists), None);
+    }
+}
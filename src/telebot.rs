@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fmt::format, io};
+use std::collections::HashMap;
 
 use crate::{
     err::FlowError,
@@ -6,11 +6,8 @@ use crate::{
     files::{read_file_into_string, yml_str_to},
 };
 use serde::{Deserialize, Serialize};
-use teloxide::error_handlers::{IgnoringErrorHandler, OnError};
 
-use teloxide::{
-    dptree::HandlerResult, prelude::*, utils::command::BotCommands, ApiError, RequestError,
-};
+use teloxide::{prelude::*, utils::command::BotCommands, ApiError, RequestError};
 use tokio::task::JoinError;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -25,6 +22,25 @@ pub fn bot_from_file(bot_cred: &str) -> Result<Bot, FlowError> {
     Ok(Bot::new(bot_cred.token))
 }
 
+pub async fn start(cfg_files: ConfigurationFiles) -> Result<(), FlowError> {
+    let bot = bot_from_file(&cfg_files.bot)?;
+
+    let handler = Update::filter_message()
+        .branch(
+            dptree::entry()
+                .filter_command::<Command>()
+                .endpoint(processing),
+        )
+        .branch(dptree::endpoint(find_word));
+
+    Dispatcher::builder(bot, handler)
+        .dependencies(dptree::deps![cfg_files])
+        .build()
+        .dispatch()
+        .await;
+    Ok(())
+}
+
 #[derive(BotCommands, Clone)]
 #[command(
     rename_rule = "lowercase",
@@ -33,15 +49,19 @@ pub fn bot_from_file(bot_cred: &str) -> Result<Bot, FlowError> {
 pub enum Command {
     #[command(description = "display this text.")]
     Help,
-    #[command(description = "run a task")]
+    #[command(
+        description = "run a task: /task name arg=value, add --dry-run to only see the changes"
+    )]
     Task(String),
     #[command(description = "task list")]
     Tasks,
+    #[command(description = "revert the changes of a run: /revert run-id")]
+    Revert(String),
 }
 
 impl From<FlowError> for RequestError {
     fn from(value: FlowError) -> Self {
-        RequestError::Api(ApiError::Unknown(format!("error: {:?}", value)))
+        RequestError::Api(ApiError::Unknown(format!("error: {}", value)))
     }
 }
 
@@ -49,6 +69,11 @@ fn je_to_re(je: JoinError) -> RequestError {
     RequestError::Api(ApiError::Unknown(format!("error: {:?}", je)))
 }
 
+fn failure(e: FlowError) -> String {
+    error!("the task is failed: {}", e);
+    format!("error: {}", e)
+}
+
 pub async fn processing(
     trello_info: ConfigurationFiles,
     bot: Bot,
@@ -61,39 +86,89 @@ pub async fn processing(
                 .await?;
         }
         Command::Tasks => {
-            let mut tasks = tokio::spawn(async move {
+            let tasks = tokio::task::spawn_blocking(move || {
                 Ok::<_, FlowError>(Executor::from(trello_info, Default::default())?.tasks())
             })
             .await
-            .map_err(je_to_re)??;
-
-            tasks.sort();
-
-            let tasks_str = tasks.join("\n");
-            bot.send_message(msg.chat.id, tasks_str).await?;
+            .map_err(je_to_re)?;
+
+            match tasks {
+                Ok(mut tasks) => {
+                    tasks.sort();
+                    bot.send_message(msg.chat.id, tasks.join("\n")).await?;
+                }
+                Err(e) => {
+                    bot.send_message(msg.chat.id, failure(e)).await?;
+                }
+            }
         }
         Command::Task(command) => {
-            let words: Vec<_> = command.split(" ").collect();
-            let task = words.first().unwrap().to_string();
+            let (flags, words): (Vec<_>, Vec<_>) = command
+                .split(' ')
+                .filter(|w| !w.is_empty())
+                .partition(|w| *w == "--dry-run");
+            let dry_run = !flags.is_empty();
+            let task = words.first().unwrap_or(&"").to_string();
             let task_str = task.clone();
             let args: HashMap<String, String> = HashMap::from_iter(
-                words[1..]
-                    .into_iter()
+                words
+                    .iter()
+                    .skip(1)
                     .map(|s| s.split("=").map(|s| s.trim()).collect::<Vec<_>>())
                     .map(|v| (v[0].to_string(), v[1].to_string())),
             );
 
-            let res = tokio::spawn(async move {
+            let res = tokio::task::spawn_blocking(move || {
                 let mut e = Executor::from(trello_info, args)?;
-                e.start(task)
+                let res = e.start(task, dry_run)?;
+                Ok::<_, FlowError>((res, e.last_run()))
             })
             .await
-            .map_err(je_to_re)??;
-
-            bot.send_message(msg.chat.id, format!("the task {} is done.", task_str))
-                .await?;
-            bot.send_message(msg.chat.id, format!("{}", res.to_string()))
-                .await?;
+            .map_err(je_to_re)?;
+
+            match res {
+                Ok((res, run)) => {
+                    let done = match run {
+                        Some(run) => format!("done, revert it with /revert {}", run),
+                        None if dry_run => "planned".to_string(),
+                        None => "done".to_string(),
+                    };
+                    bot.send_message(msg.chat.id, format!("the task {} is {}.", task_str, done))
+                        .await?;
+                    bot.send_message(msg.chat.id, res.to_string()).await?;
+                }
+                Err(e) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!("the task {} is failed: {}", task_str, failure(e)),
+                    )
+                    .await?;
+                }
+            }
+        }
+        Command::Revert(run) => {
+            let run = run.trim().to_string();
+            let run_str = run.clone();
+            let res = tokio::task::spawn_blocking(move || {
+                Executor::from(trello_info, Default::default())?.revert(&run)
+            })
+            .await
+            .map_err(je_to_re)?;
+
+            match res {
+                Ok(res) => {
+                    bot.send_message(msg.chat.id, format!("the run {} is reverted.", run_str))
+                        .await?;
+                    bot.send_message(msg.chat.id, res.to_string()).await?;
+                }
+                Err(e) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!("the run {} is not reverted: {}", run_str, failure(e)),
+                    )
+                    .await?;
+                }
+            }
         }
     };
 
@@ -115,14 +190,16 @@ pub async fn find_word(files: ConfigurationFiles, bot: Bot, msg: Message) -> Res
 
     let bot_cred: BotCred = yml_str_to(read_file_into_string(files.bot.as_str())?.as_str())?;
 
-    let res = tokio::spawn(async move {
+    let res = tokio::task::spawn_blocking(move || {
         let mut e = Executor::from(files, args)?;
-        e.start(bot_cred.task)
+        e.start(bot_cred.task, false)
     })
     .await
-    .map_err(je_to_re)??;
+    .map_err(je_to_re)?;
 
-    bot.send_message(msg.chat.id, format!("{}", res.to_string()))
-        .await?;
+    match res {
+        Ok(res) => bot.send_message(msg.chat.id, res.to_string()).await?,
+        Err(e) => bot.send_message(msg.chat.id, failure(e)).await?,
+    };
     Ok(())
 }
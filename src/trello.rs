@@ -1,23 +1,116 @@
 use crate::{
     err::FlowError,
     files::{read_file_into_string, yml_str_to},
+    task::tasks::CopyField,
 };
-use serde::{Deserialize, Serialize};
-use std::borrow::{Borrow, BorrowMut};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use self::retry::{RateLimiter, RetryPolicy};
+
+#[cfg(test)]
+pub mod fake;
+mod retry;
+
+/// The operations the executor needs from a trello board.
+/// The lookups by name are derived from the listing methods.
+#[allow(dead_code)]
+pub trait TrelloApi {
+    fn boards(&self) -> Result<Vec<Board>, FlowError>;
+    fn cards(&self, board_id: &str) -> Result<Vec<Card>, FlowError>;
+    fn archived_cards(&self, board_id: &str) -> Result<Vec<Card>, FlowError>;
+    fn lists(&self, board_id: &str) -> Result<Vec<List>, FlowError>;
+    fn cards_in_list(&self, list_id: &str) -> Result<Vec<Card>, FlowError>;
+    fn labels(&self, board_id: &str) -> Result<Vec<Label>, FlowError>;
+
+    fn create_card(&self, list_id: &str, card_name: &str, pos: &str) -> Result<Card, FlowError>;
+    /// Creates a copy of the card with its name and the fields in `keep`.
+    fn copy_card(
+        &self,
+        source: &Card,
+        list_id: &str,
+        pos: &str,
+        keep: &[CopyField],
+    ) -> Result<Card, FlowError>;
+    fn update_card_name(&self, id: &str, new_name: &str) -> Result<Card, FlowError>;
+    fn update_card_list(&self, card_id: &str, card_list: &str) -> Result<Card, FlowError>;
+    fn mov_card(&self, card_id: &str, card_list: &str, pos: &str) -> Result<Card, FlowError>;
+    /// Moves the card to the list of another board.
+    fn mov_card_to_board(
+        &self,
+        card_id: &str,
+        board_id: &str,
+        card_list: &str,
+        pos: &str,
+    ) -> Result<Card, FlowError>;
+    fn update_card_dsc(&self, card_id: &str, desc: &str) -> Result<Card, FlowError>;
+    fn archive_card(&self, card_id: &str) -> Result<Card, FlowError>;
+    fn unarchive_card(&self, card_id: &str) -> Result<Card, FlowError>;
+    fn delete_card(&self, card_id: &str) -> Result<(), FlowError>;
+    fn add_label(&self, card_id: &str, label_id: &str) -> Result<(), FlowError>;
+    fn remove_label(&self, card_id: &str, label_id: &str) -> Result<(), FlowError>;
+    fn create_label(
+        &self,
+        board_id: &str,
+        name: &str,
+        color: Option<&str>,
+    ) -> Result<Label, FlowError>;
+    fn delete_label(&self, label_id: &str) -> Result<(), FlowError>;
+
+    fn search_cards_manually(
+        &self,
+        board_id: &str,
+        card_name: &str,
+    ) -> Result<Vec<Card>, FlowError> {
+        Ok(self
+            .cards(board_id)?
+            .into_iter()
+            .filter(|v| v.name.to_lowercase() == card_name.to_lowercase())
+            .collect())
+    }
+    fn list_by_name(&self, board_id: &str, name: &str) -> Result<Option<List>, FlowError> {
+        Ok(self.lists(board_id)?.into_iter().find(|l| l.name == name))
+    }
+    fn label_by_name(
+        &self,
+        board_id: &str,
+        name: &str,
+        case: bool,
+    ) -> Result<Option<Label>, FlowError> {
+        Ok(find_label(&self.labels(board_id)?, name, case).cloned())
+    }
+}
+
+/// Finds a label by name, `case` makes the comparison case-insensitive.
+pub fn find_label<'a>(labels: &'a [Label], name: &str, case: bool) -> Option<&'a Label> {
+    labels.iter().find(|l| {
+        if case {
+            l.name.to_lowercase() == name.to_lowercase()
+        } else {
+            l.name == name
+        }
+    })
+}
 
 #[derive(Clone, Debug)]
 pub struct TrelloConnector {
     prefix: &'static str,
     cred: TrelloCred,
+    limiter: Arc<Mutex<RateLimiter>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TrelloCred {
     key: String,
     token: String,
+    #[serde(default)]
+    retry: RetryPolicy,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Board {
     pub id: String,
     pub name: String,
@@ -32,294 +125,468 @@ pub struct Card {
     pub name: String,
     pub desc: String,
     pub id_list: String,
+    #[serde(default)]
+    pub id_board: String,
     pub id_labels: Vec<String>,
     pub url: String,
     pub short_url: String,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default)]
+    pub date_last_activity: Option<String>,
+    #[serde(default)]
+    pub due: Option<String>,
+}
+
+impl Card {
+    /// The creation time in seconds, trello puts it into the first 8 hex digits of the id.
+    pub fn created(&self) -> Option<u32> {
+        self.id
+            .get(..8)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct List {
     pub id: String,
     pub name: String,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Label {
     pub id: String,
     pub name: String,
+    /// absent for the labels without a colour
+    #[serde(default)]
+    pub color: Option<String>,
 }
 
 impl TrelloConnector {
     pub fn from_file(path: &str) -> Result<TrelloConnector, FlowError> {
+        let cred: TrelloCred = yml_str_to(read_file_into_string(path)?.as_str())?;
         Ok(TrelloConnector {
             prefix: "https://api.trello.com",
-            cred: yml_str_to(read_file_into_string(path)?.as_str())?,
+            limiter: cred.retry.shared_limiter(&cred.token),
+            cred,
         })
     }
 }
 
 impl TrelloConnector {
-    fn get_req<T>(&self, url: &str) -> std::io::Result<T>
+    fn call<T>(
+        &self,
+        req: ureq::Request,
+        params: Vec<(&str, &str)>,
+        idempotent: bool,
+    ) -> Result<T, FlowError>
     where
         T: for<'de> Deserialize<'de>,
     {
-        ureq::get(format!("{}{}", self.prefix, url).as_str())
-            .query("key", self.cred.key.as_str())
-            .query("token", self.cred.token.as_str())
-            .set("Accept", "application/json")
-            .call()
-            .expect("should get the result")
-            .into_json::<T>()
-    }
-    fn get_params_req<T>(&self, url: &str, params: Vec<(&str, &str)>) -> std::io::Result<T>
-    where
-        T: for<'de> Deserialize<'de>,
-    {
-        let mut r = ureq::get(format!("{}{}", self.prefix, url).as_str())
+        let mut r = req
             .query("key", self.cred.key.as_str())
             .query("token", self.cred.token.as_str())
             .set("Accept", "application/json");
+
         for (k, v) in params.into_iter() {
             r = r.query(k, v);
         }
-        r.call().expect("should get the result").into_json::<T>()
+
+        let mut attempt = 0;
+        loop {
+            RateLimiter::acquire(&self.limiter);
+            match r.clone().call() {
+                Ok(resp) => {
+                    return resp
+                        .into_json::<T>()
+                        .map_err(|e| FlowError::JsonError(e.to_string()))
+                }
+                Err(e) => {
+                    let delay = self.cred.retry.retry_delay(
+                        &e,
+                        attempt,
+                        idempotent,
+                        &mut rand::thread_rng(),
+                    );
+                    match delay {
+                        Some(delay) => {
+                            attempt += 1;
+                            warn!(
+                                "the request {} {} failed: {}, retry {} of {} in {:?}",
+                                r.method(),
+                                r.url(),
+                                e.kind(),
+                                attempt,
+                                self.cred.retry.attempts,
+                                delay
+                            );
+                            thread::sleep(delay);
+                        }
+                        None => return Err(e.into()),
+                    }
+                }
+            }
+        }
     }
-    fn post_req<T>(&self, url: &str, params: Vec<(&str, &str)>) -> std::io::Result<T>
+
+    fn get_req<T>(&self, url: &str) -> Result<T, FlowError>
     where
         T: for<'de> Deserialize<'de>,
     {
-        let mut r = ureq::post(format!("{}{}", self.prefix, url).as_str())
-            .query("key", self.cred.key.as_str())
-            .query("token", self.cred.token.as_str())
-            .set("Accept", "application/json");
-
-        for (k, v) in params.into_iter() {
-            r = r.query(k, v);
-        }
-        r.call().expect("should get the result").into_json::<T>()
+        self.call(
+            ureq::get(format!("{}{}", self.prefix, url).as_str()),
+            vec![],
+            true,
+        )
+    }
+    fn post_req<T>(&self, url: &str, params: Vec<(&str, &str)>) -> Result<T, FlowError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.call(
+            ureq::post(format!("{}{}", self.prefix, url).as_str()),
+            params,
+            false,
+        )
     }
 
-    fn put_req<T>(&self, url: &str, params: Vec<(&str, &str)>) -> std::io::Result<T>
+    fn put_req<T>(&self, url: &str, params: Vec<(&str, &str)>) -> Result<T, FlowError>
     where
         T: for<'de> Deserialize<'de>,
     {
-        let mut r = ureq::put(format!("{}{}", self.prefix, url).as_str())
-            .query("key", self.cred.key.as_str())
-            .query("token", self.cred.token.as_str())
-            .set("Accept", "application/json");
+        self.call(
+            ureq::put(format!("{}{}", self.prefix, url).as_str()),
+            params,
+            true,
+        )
+    }
 
-        for (k, v) in params.into_iter() {
-            r = r.query(k, v);
-        }
-        r.call().expect("should get the result").into_json::<T>()
+    fn delete_req(&self, url: &str) -> Result<(), FlowError> {
+        self.call::<IgnoredAny>(
+            ureq::delete(format!("{}{}", self.prefix, url).as_str()),
+            vec![],
+            true,
+        )
+        .map(|_| ())
     }
 }
 
-impl TrelloConnector {
-    pub fn search_cards_manually(&self, board_id: &str, card_name: &str) -> Vec<Card> {
-        self.cards(board_id)
-            .into_iter()
-            .filter(|v| v.name.to_lowercase() == card_name.to_lowercase())
-            .collect()
-    }
-
-    // pub fn find_cards(&self, board_id: &str, query: &str) -> Vec<Card> {
-    //     self.get_params_req::<Vec<Card>>(
-    //         "/1/search",
-    //         vec![
-    //             ("query", query),
-    //             ("idBoards", board_id),
-    //             ("modelTypes", "cards"),
-    //         ],
-    //     )
-    //     .expect("list of cards")
-    // }
-
-    pub fn boards(&self) -> Vec<Board> {
-        self.get_req::<Vec<Board>>("/1/members/me/boards")
-            .expect("get boards")
+impl TrelloApi for TrelloConnector {
+    fn boards(&self) -> Result<Vec<Board>, FlowError> {
+        Ok(self
+            .get_req::<Vec<Board>>("/1/members/me/boards")?
             .into_iter()
             .filter(|b| !b.closed)
-            .collect()
+            .collect())
     }
-    pub fn cards(&self, board_id: &str) -> Vec<Card> {
-        self.get_req::<Vec<Card>>(format!("/1/boards/{}/cards", board_id).as_str())
-            .expect("get cards")
+    fn cards(&self, board_id: &str) -> Result<Vec<Card>, FlowError> {
+        self.get_req(format!("/1/boards/{}/cards", board_id).as_str())
     }
-    pub fn lists(&self, board_id: &str) -> Vec<List> {
-        self.get_req::<Vec<List>>(format!("/1/boards/{}/lists", board_id).as_str())
-            .expect("get lists")
+    fn archived_cards(&self, board_id: &str) -> Result<Vec<Card>, FlowError> {
+        self.get_req(format!("/1/boards/{}/cards/closed", board_id).as_str())
     }
-    pub fn cards_in_list(&self, list_id: &str) -> Vec<Card> {
-        self.get_req::<Vec<Card>>(format!("/1/lists/{}/cards", list_id).as_str())
-            .expect("get lists")
+    fn lists(&self, board_id: &str) -> Result<Vec<List>, FlowError> {
+        self.get_req(format!("/1/boards/{}/lists", board_id).as_str())
     }
-    pub fn labels(&self, board_id: &str) -> Vec<Label> {
-        self.get_req::<Vec<Label>>(format!("/1/boards/{}/labels", board_id).as_str())
-            .expect("get labels")
+    fn cards_in_list(&self, list_id: &str) -> Result<Vec<Card>, FlowError> {
+        self.get_req(format!("/1/lists/{}/cards", list_id).as_str())
     }
-    pub fn list_by_name(&self, board_id: &str, name: &str) -> Option<List> {
-        self.get_req::<Vec<List>>(format!("/1/boards/{}/lists", board_id).as_str())
-            .expect("get lists")
-            .into_iter()
-            .find(|l| l.name == name)
-    }
-    pub fn label_by_name(&self, board_id: &str, name: &str, case: bool) -> Option<Label> {
-        self.labels(board_id).into_iter().find(|l| {
-            if case {
-                l.name.to_lowercase() == name.to_lowercase()
-            } else {
-                l.name == name
-            }
-        })
+    fn labels(&self, board_id: &str) -> Result<Vec<Label>, FlowError> {
+        self.get_req(format!("/1/boards/{}/labels", board_id).as_str())
     }
 
-    pub fn create_card(&self, list_id: &str, card_name: &str, pos: &str) -> Card {
-        self.post_req::<Card>(
-            format!("/1/cards").as_str(),
+    fn create_card(&self, list_id: &str, card_name: &str, pos: &str) -> Result<Card, FlowError> {
+        self.post_req(
+            "/1/cards",
             vec![("pos", pos), ("idList", list_id), ("name", card_name)],
         )
-        .expect("create card")
     }
-    pub fn update_card_name(&self, id: &str, new_name: &str) -> Card {
-        self.put_req::<Card>(
+    fn copy_card(
+        &self,
+        source: &Card,
+        list_id: &str,
+        pos: &str,
+        keep: &[CopyField],
+    ) -> Result<Card, FlowError> {
+        if keep.is_empty() {
+            return self.create_card(list_id, &source.name, pos);
+        }
+        let mut params = vec![
+            ("pos", pos),
+            ("idList", list_id),
+            ("name", source.name.as_str()),
+        ];
+        // the description is not among the fields trello copies, so it is sent as is
+        if keep.contains(&CopyField::Desc) {
+            params.push(("desc", source.desc.as_str()));
+        }
+        let from_source: Vec<&str> = keep
+            .iter()
+            .filter(|k| **k != CopyField::Desc)
+            .map(CopyField::name)
+            .collect();
+        let from_source = from_source.join(",");
+        if !from_source.is_empty() {
+            params.push(("idCardSource", source.id.as_str()));
+            params.push(("keepFromSource", from_source.as_str()));
+        }
+        self.post_req("/1/cards", params)
+    }
+    fn update_card_name(&self, id: &str, new_name: &str) -> Result<Card, FlowError> {
+        self.put_req(
             format!("/1/cards/{}", id).as_str(),
             vec![("name", new_name)],
         )
-        .expect("update card")
     }
-    pub fn update_card_list(&self, card_id: &str, card_list: &str) -> Card {
-        self.put_req::<Card>(
+    fn update_card_list(&self, card_id: &str, card_list: &str) -> Result<Card, FlowError> {
+        self.put_req(
             format!("/1/cards/{}", card_id).as_str(),
             vec![("idList", card_list)],
         )
-        .expect("update card list")
     }
-    pub fn mov_card(&self, card_id: &str, card_list: &str, pos: &str) -> Card {
-        self.put_req::<Card>(
+    fn mov_card(&self, card_id: &str, card_list: &str, pos: &str) -> Result<Card, FlowError> {
+        self.put_req(
             format!("/1/cards/{}", card_id).as_str(),
             vec![("idList", card_list), ("pos", pos)],
         )
-        .expect("move card to list")
     }
-    pub fn update_card_dsc(&self, card_id: &str, desc: &str) -> Card {
-        self.put_req::<Card>(
+    fn mov_card_to_board(
+        &self,
+        card_id: &str,
+        board_id: &str,
+        card_list: &str,
+        pos: &str,
+    ) -> Result<Card, FlowError> {
+        self.put_req(
+            format!("/1/cards/{}", card_id).as_str(),
+            vec![("idBoard", board_id), ("idList", card_list), ("pos", pos)],
+        )
+    }
+    fn update_card_dsc(&self, card_id: &str, desc: &str) -> Result<Card, FlowError> {
+        self.put_req(
             format!("/1/cards/{}", card_id).as_str(),
             vec![("desc", desc)],
         )
-        .expect("update card desc")
+    }
+    fn archive_card(&self, card_id: &str) -> Result<Card, FlowError> {
+        self.put_req(
+            format!("/1/cards/{}", card_id).as_str(),
+            vec![("closed", "true")],
+        )
+    }
+    fn unarchive_card(&self, card_id: &str) -> Result<Card, FlowError> {
+        self.put_req(
+            format!("/1/cards/{}", card_id).as_str(),
+            vec![("closed", "false")],
+        )
+    }
+    fn delete_card(&self, card_id: &str) -> Result<(), FlowError> {
+        self.delete_req(format!("/1/cards/{}", card_id).as_str())
+    }
+    fn add_label(&self, card_id: &str, label_id: &str) -> Result<(), FlowError> {
+        self.post_req::<IgnoredAny>(
+            format!("/1/cards/{}/idLabels", card_id).as_str(),
+            vec![("value", label_id)],
+        )
+        .map(|_| ())
+    }
+    fn remove_label(&self, card_id: &str, label_id: &str) -> Result<(), FlowError> {
+        self.delete_req(format!("/1/cards/{}/idLabels/{}", card_id, label_id).as_str())
+    }
+    fn create_label(
+        &self,
+        board_id: &str,
+        name: &str,
+        color: Option<&str>,
+    ) -> Result<Label, FlowError> {
+        let mut params = vec![("idBoard", board_id), ("name", name)];
+        if let Some(color) = color {
+            params.push(("color", color));
+        }
+        self.post_req("/1/labels", params)
+    }
+    fn delete_label(&self, label_id: &str) -> Result<(), FlowError> {
+        self.delete_req(format!("/1/labels/{}", label_id).as_str())
     }
 }
 
+#[cfg(test)]
 mod tests {
-    use crate::trello::{TrelloConnector, TrelloCred};
+    use crate::{
+        err::FlowError,
+        trello::{fake::FakeBoard, TrelloApi},
+    };
+
+    fn board() -> FakeBoard {
+        FakeBoard::new("ENG")
+            .list("Archive")
+            .list("Queue")
+            .label("Demand")
+            .card("Archive", "collapse", &["Demand"])
+            .card("Archive", "rely on", &[])
+            .card("Queue", "get along", &[])
+    }
 
     #[test]
     fn labels_test() {
-        let trello =
-            TrelloConnector::from_file("/home/besok/projects/trello-flow/examples/trello_cred.yml")
-                .unwrap();
+        let trello = board();
         let b = trello
             .boards()
+            .unwrap()
             .into_iter()
             .find(|b| b.name == "ENG")
             .unwrap();
-        println!("{:?}", trello.labels(&b.id));
+        let labels = trello.labels(&b.id).unwrap();
+        assert_eq!(labels.len(), 1);
+        assert!(trello
+            .label_by_name(&b.id, "demand", true)
+            .unwrap()
+            .is_some());
+        assert!(trello
+            .label_by_name(&b.id, "demand", false)
+            .unwrap()
+            .is_none());
     }
 
     #[test]
     fn card_search() {
-        let trello =
-            TrelloConnector::from_file("/home/besok/projects/trello-flow/examples/trello_cred.yml")
-                .unwrap();
+        let trello = board();
         let b = trello
             .boards()
+            .unwrap()
             .into_iter()
             .find(|b| b.name == "ENG")
             .unwrap();
-        println!("{:?}", trello.search_cards_manually(&b.id, "collapse"));
+        let cards = trello.search_cards_manually(&b.id, "Collapse").unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].name, "collapse");
     }
 
     #[test]
-    fn boards_test() {
-        let trello = TrelloConnector::from_file(
-            "/home/bzhg/projects/trello-vocab-loader/examples/trello_cred.yml",
-        )
-        .unwrap();
-        let boards = trello.boards();
-        println!("{:?}", boards)
-    }
-
-    #[test]
-    fn cards_test() {
-        let trello = TrelloConnector::from_file(
-            "/home/bzhg/projects/trello-vocab-loader/examples/trello_cred.yml",
-        )
-        .unwrap();
-        let boards = trello.boards();
-        println!("{:?}", boards);
+    fn cards_list_test() {
+        let trello = board();
+        let bid = trello.boards().unwrap().remove(0);
 
-        for b in boards.iter() {
-            let cards = trello.cards(b.id.as_str());
+        let list = trello
+            .lists(bid.id.as_str())
+            .unwrap()
+            .into_iter()
+            .find(|s| s.name == "Archive")
+            .unwrap();
 
-            println!("{:?}", cards);
-        }
+        let names: Vec<String> = trello
+            .cards_in_list(&list.id)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, vec!["collapse", "rely on"]);
+        assert_eq!(trello.cards(&bid.id).unwrap().len(), 3);
     }
+
     #[test]
-    fn cards_list_test() {
-        let trello = TrelloConnector::from_file(
-            "/home/bzhg/projects/trello-vocab-loader/examples/trello_cred.yml",
-        )
-        .unwrap();
-        let boards = trello.boards();
-        println!("{:?}", boards);
+    fn update_card_test() {
+        let trello = board();
+        let eng_id = trello.boards().unwrap().remove(0).id;
+        let queue = trello.list_by_name(&eng_id, "Queue").unwrap().unwrap();
+        let c = trello
+            .search_cards_manually(&eng_id, "rely on")
+            .unwrap()
+            .remove(0);
 
-        let bid = boards
-            .into_iter()
-            .find(|s| s.name == "ENG".to_string())
+        trello
+            .update_card_list(c.id.as_str(), queue.id.as_str())
             .unwrap();
+        assert_eq!(trello.card_names("Queue"), vec!["get along", "rely on"]);
 
-        let list = trello
-            .lists(bid.id.as_str())
-            .into_iter()
-            .find(|s| s.name == "Idioms".to_string())
+        trello
+            .mov_card(c.id.as_str(), queue.id.as_str(), "top")
             .unwrap();
+        assert_eq!(trello.card_names("Queue"), vec!["rely on", "get along"]);
 
-        for b in trello.cards_in_list(&list.id) {
-            println!("{:?}", b);
-        }
+        trello.update_card_name(c.id.as_str(), "rely upon").unwrap();
+        trello
+            .update_card_dsc(c.id.as_str(), "to depend on")
+            .unwrap();
+        let c = trello
+            .search_cards_manually(&eng_id, "rely upon")
+            .unwrap()
+            .remove(0);
+        assert_eq!(c.desc, "to depend on");
     }
 
     #[test]
-    fn lists_test() {
-        let trello = TrelloConnector::from_file(
-            "/Users/boriszhguchev/projects/trello-vocab-loader/example/trello_token.json",
-        )
-        .unwrap();
-        let boards = trello.boards();
-        println!("{:?}", boards);
+    fn unknown_ids_test() {
+        let trello = board();
+        assert!(matches!(
+            trello.cards_in_list("nowhere"),
+            Err(FlowError::HttpError(404, _))
+        ));
+        assert!(matches!(
+            trello.update_card_name("nothing", "name"),
+            Err(FlowError::HttpError(404, _))
+        ));
+    }
 
-        for b in boards.iter() {
-            let lists = trello.lists(b.id.as_str());
+    #[test]
+    fn create_card_test() {
+        let trello = board();
+        let eng_id = trello.boards().unwrap().remove(0).id;
+        let queue = trello.list_by_name(&eng_id, "Queue").unwrap().unwrap();
 
-            println!("{:?}", lists);
-        }
+        trello.create_card(&queue.id, "break down", "top").unwrap();
+        trello.create_card(&queue.id, "carry on", "bottom").unwrap();
+        assert_eq!(
+            trello.card_names("Queue"),
+            vec!["break down", "get along", "carry on"]
+        );
     }
 
     #[test]
-    fn update_card_test() {
-        let trello = TrelloConnector::from_file("~/.trello/cred.json").unwrap();
-        let eng_id = trello
-            .boards()
-            .into_iter()
-            .find(|b| b.name.eq("ENG"))
-            .map(|b| b.id)
-            .expect("");
-        let cards = trello.cards(eng_id.as_str());
-        let c = cards.first().unwrap();
-        let lists = trello.lists(&*eng_id);
-        let list = lists.iter().find(|l| l.name == "Queue").unwrap();
-
-        trello.update_card_list(c.id.as_str(), list.id.as_str());
-        println!("card:{}", c.name)
+    fn archive_card_test() {
+        let trello = board();
+        let eng_id = trello.boards().unwrap().remove(0).id;
+        let c = trello
+            .search_cards_manually(&eng_id, "collapse")
+            .unwrap()
+            .remove(0);
+
+        trello.archive_card(&c.id).unwrap();
+        assert_eq!(trello.card_names("Archive"), vec!["rely on"]);
+        assert_eq!(trello.archived_cards(&eng_id).unwrap()[0].name, "collapse");
+
+        trello.unarchive_card(&c.id).unwrap();
+        assert_eq!(trello.card_names("Archive"), vec!["collapse", "rely on"]);
+        assert!(trello.archived_cards(&eng_id).unwrap().is_empty());
+
+        trello.delete_card(&c.id).unwrap();
+        assert_eq!(trello.card_names("Archive"), vec!["rely on"]);
+        assert!(matches!(
+            trello.delete_card(&c.id),
+            Err(FlowError::HttpError(404, _))
+        ));
+    }
+
+    #[test]
+    fn card_labels_test() {
+        let trello = board();
+        let eng_id = trello.boards().unwrap().remove(0).id;
+        let c = trello
+            .search_cards_manually(&eng_id, "rely on")
+            .unwrap()
+            .remove(0);
+
+        let fresh = trello.create_label(&eng_id, "Fresh", Some("blue")).unwrap();
+        assert_eq!(trello.labels(&eng_id).unwrap().len(), 2);
+        trello.add_label(&c.id, &fresh.id).unwrap();
+        let labels = |trello: &FakeBoard| {
+            trello
+                .search_cards_manually(&eng_id, "rely on")
+                .unwrap()
+                .remove(0)
+                .id_labels
+        };
+        assert_eq!(labels(&trello), vec![fresh.id.clone()]);
+        trello.remove_label(&c.id, &fresh.id).unwrap();
+        assert!(labels(&trello).is_empty());
     }
 }
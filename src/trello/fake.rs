@@ -0,0 +1,432 @@
+use std::cell::RefCell;
+
+use super::{Board, Card, Label, List, TrelloApi};
+use crate::err::FlowError;
+use crate::task::tasks::CopyField;
+
+fn not_found(what: &str, id: &str) -> FlowError {
+    FlowError::HttpError(404, format!("the {} {} is not found", what, id))
+}
+
+/// An in-memory trello used to run the executor offline.
+/// The builder methods add lists, labels and cards to the last added board.
+#[derive(Default, Debug)]
+pub struct FakeBoard {
+    state: RefCell<FakeState>,
+}
+
+#[derive(Default, Debug)]
+struct FakeState {
+    seq: usize,
+    reads: usize,
+    boards: Vec<Board>,
+    // (board id, list)
+    lists: Vec<(String, List)>,
+    // (board id, label)
+    labels: Vec<(String, Label)>,
+    cards: Vec<Card>,
+}
+
+impl FakeState {
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.seq += 1;
+        format!("{}{}", prefix, self.seq)
+    }
+
+    fn last_board(&self) -> String {
+        self.boards
+            .last()
+            .map(|b| b.id.clone())
+            .expect("a board should be added first")
+    }
+
+    fn list_id(&self, name: &str) -> String {
+        let board = self.last_board();
+        self.lists
+            .iter()
+            .find(|(b, l)| *b == board && l.name == name)
+            .map(|(_, l)| l.id.clone())
+            .unwrap_or_else(|| panic!("the list {} should be added first", name))
+    }
+
+    fn board_of_list(&self, list_id: &str) -> Option<String> {
+        self.lists
+            .iter()
+            .find(|(_, l)| l.id == list_id)
+            .map(|(b, _)| b.clone())
+    }
+
+    fn in_list(&self, list_id: &str) -> Vec<Card> {
+        let mut cards: Vec<Card> = self
+            .cards
+            .iter()
+            .filter(|c| c.id_list == list_id && !c.closed)
+            .cloned()
+            .collect();
+        cards.sort_by(|l, r| l.pos.total_cmp(&r.pos));
+        cards
+    }
+
+    /// Translates a trello position (top, bottom or a number) into a number.
+    fn pos(&self, list_id: &str, pos: &str) -> Result<f32, FlowError> {
+        if self.board_of_list(list_id).is_none() {
+            return Err(not_found("list", list_id));
+        }
+        let cards = self.in_list(list_id);
+        match pos {
+            "top" => Ok(cards.first().map(|c| c.pos / 2.0).unwrap_or(65536.0)),
+            "bottom" => Ok(cards.last().map(|c| c.pos + 65536.0).unwrap_or(65536.0)),
+            n => n
+                .parse()
+                .map_err(|_| FlowError::HttpError(400, format!("invalid value for pos: {}", n))),
+        }
+    }
+
+    fn card_mut(&mut self, id: &str) -> Result<&mut Card, FlowError> {
+        self.cards
+            .iter_mut()
+            .find(|c| c.id == id)
+            .ok_or_else(|| not_found("card", id))
+    }
+}
+
+impl FakeBoard {
+    pub fn new(board: &str) -> Self {
+        FakeBoard::default().board(board)
+    }
+
+    pub fn board(self, name: &str) -> Self {
+        {
+            let mut st = self.state.borrow_mut();
+            let id = st.next_id("board");
+            st.boards.push(Board {
+                id,
+                name: name.to_string(),
+                closed: false,
+            });
+        }
+        self
+    }
+
+    pub fn list(self, name: &str) -> Self {
+        {
+            let mut st = self.state.borrow_mut();
+            let board = st.last_board();
+            let id = st.next_id("list");
+            st.lists.push((
+                board,
+                List {
+                    id,
+                    name: name.to_string(),
+                },
+            ));
+        }
+        self
+    }
+
+    pub fn label(self, name: &str) -> Self {
+        self.colored_label(name, None)
+    }
+
+    pub fn colored_label(self, name: &str, color: Option<&str>) -> Self {
+        {
+            let mut st = self.state.borrow_mut();
+            let board = st.last_board();
+            let id = st.next_id("label");
+            st.labels.push((
+                board,
+                Label {
+                    id,
+                    name: name.to_string(),
+                    color: color.map(ToString::to_string),
+                },
+            ));
+        }
+        self
+    }
+
+    pub fn card(self, list: &str, name: &str, labels: &[&str]) -> Self {
+        {
+            let mut st = self.state.borrow_mut();
+            let board = st.last_board();
+            let list_id = st.list_id(list);
+            let id_labels = labels
+                .iter()
+                .map(|name| {
+                    st.labels
+                        .iter()
+                        .find(|(b, l)| *b == board && l.name == *name)
+                        .map(|(_, l)| l.id.clone())
+                        .unwrap_or_else(|| panic!("the label {} should be added first", name))
+                })
+                .collect();
+            let pos = st.pos(&list_id, "bottom").unwrap();
+            let id = st.next_id("card");
+            st.cards.push(Card {
+                url: format!("https://trello.com/c/{}", id),
+                short_url: format!("https://trello.com/c/{}", id),
+                id,
+                pos,
+                name: name.to_string(),
+                desc: String::new(),
+                id_list: list_id,
+                id_board: board,
+                id_labels,
+                closed: false,
+                date_last_activity: None,
+                due: None,
+            });
+        }
+        self
+    }
+
+    /// The number of the read requests made so far.
+    pub fn reads(&self) -> usize {
+        self.state.borrow().reads
+    }
+
+    /// The names of the cards in the given list of the last added board, top to bottom.
+    pub fn card_names(&self, list: &str) -> Vec<String> {
+        let st = self.state.borrow();
+        st.in_list(&st.list_id(list))
+            .into_iter()
+            .map(|c| c.name)
+            .collect()
+    }
+
+    /// The names of the cards in the given list of the named board, top to bottom.
+    pub fn board_card_names(&self, board: &str, list: &str) -> Vec<String> {
+        let st = self.state.borrow();
+        let board = st
+            .boards
+            .iter()
+            .find(|b| b.name == board)
+            .map(|b| b.id.clone())
+            .unwrap_or_else(|| panic!("the board {} should be added first", board));
+        st.lists
+            .iter()
+            .find(|(b, l)| *b == board && l.name == list)
+            .map(|(_, l)| st.in_list(&l.id))
+            .unwrap_or_else(|| panic!("the list {} should be added first", list))
+            .into_iter()
+            .map(|c| c.name)
+            .collect()
+    }
+}
+
+impl TrelloApi for FakeBoard {
+    fn boards(&self) -> Result<Vec<Board>, FlowError> {
+        self.state.borrow_mut().reads += 1;
+        Ok(self.state.borrow().boards.clone())
+    }
+
+    fn cards(&self, board_id: &str) -> Result<Vec<Card>, FlowError> {
+        self.state.borrow_mut().reads += 1;
+        let st = self.state.borrow();
+        Ok(st
+            .lists
+            .iter()
+            .filter(|(b, _)| b == board_id)
+            .flat_map(|(_, l)| st.in_list(&l.id))
+            .collect())
+    }
+
+    fn archived_cards(&self, board_id: &str) -> Result<Vec<Card>, FlowError> {
+        self.state.borrow_mut().reads += 1;
+        let st = self.state.borrow();
+        Ok(st
+            .cards
+            .iter()
+            .filter(|c| c.closed && st.board_of_list(&c.id_list).as_deref() == Some(board_id))
+            .cloned()
+            .collect())
+    }
+
+    fn lists(&self, board_id: &str) -> Result<Vec<List>, FlowError> {
+        self.state.borrow_mut().reads += 1;
+        Ok(self
+            .state
+            .borrow()
+            .lists
+            .iter()
+            .filter(|(b, _)| b == board_id)
+            .map(|(_, l)| l.clone())
+            .collect())
+    }
+
+    fn cards_in_list(&self, list_id: &str) -> Result<Vec<Card>, FlowError> {
+        self.state.borrow_mut().reads += 1;
+        let st = self.state.borrow();
+        st.board_of_list(list_id)
+            .map(|_| st.in_list(list_id))
+            .ok_or_else(|| not_found("list", list_id))
+    }
+
+    fn labels(&self, board_id: &str) -> Result<Vec<Label>, FlowError> {
+        self.state.borrow_mut().reads += 1;
+        Ok(self
+            .state
+            .borrow()
+            .labels
+            .iter()
+            .filter(|(b, _)| b == board_id)
+            .map(|(_, l)| l.clone())
+            .collect())
+    }
+
+    fn create_card(&self, list_id: &str, card_name: &str, pos: &str) -> Result<Card, FlowError> {
+        let mut st = self.state.borrow_mut();
+        let pos = st.pos(list_id, pos)?;
+        let id = st.next_id("card");
+        let card = Card {
+            url: format!("https://trello.com/c/{}", id),
+            short_url: format!("https://trello.com/c/{}", id),
+            id,
+            pos,
+            name: card_name.to_string(),
+            desc: String::new(),
+            id_list: list_id.to_string(),
+            id_board: st.board_of_list(list_id).unwrap_or_default(),
+            id_labels: vec![],
+            closed: false,
+            date_last_activity: None,
+            due: None,
+        };
+        st.cards.push(card.clone());
+        Ok(card)
+    }
+
+    /// Only the description, the labels and the due date are kept, the fake cards have no other fields.
+    fn copy_card(
+        &self,
+        source: &Card,
+        list_id: &str,
+        pos: &str,
+        keep: &[CopyField],
+    ) -> Result<Card, FlowError> {
+        let card = self.create_card(list_id, &source.name, pos)?;
+        let mut st = self.state.borrow_mut();
+        let source = st.card_mut(&source.id)?.clone();
+        let copy = st.card_mut(&card.id)?;
+        if keep.contains(&CopyField::Desc) {
+            copy.desc = source.desc;
+        }
+        if keep.contains(&CopyField::Labels) {
+            copy.id_labels = source.id_labels;
+        }
+        if keep.contains(&CopyField::Due) {
+            copy.due = source.due;
+        }
+        Ok(copy.clone())
+    }
+
+    fn update_card_name(&self, id: &str, new_name: &str) -> Result<Card, FlowError> {
+        let mut st = self.state.borrow_mut();
+        let card = st.card_mut(id)?;
+        card.name = new_name.to_string();
+        Ok(card.clone())
+    }
+
+    fn update_card_list(&self, card_id: &str, card_list: &str) -> Result<Card, FlowError> {
+        self.mov_card(card_id, card_list, "bottom")
+    }
+
+    fn mov_card(&self, card_id: &str, card_list: &str, pos: &str) -> Result<Card, FlowError> {
+        let mut st = self.state.borrow_mut();
+        let pos = st.pos(card_list, pos)?;
+        let board = st.board_of_list(card_list).unwrap_or_default();
+        let card = st.card_mut(card_id)?;
+        card.id_list = card_list.to_string();
+        card.id_board = board;
+        card.pos = pos;
+        Ok(card.clone())
+    }
+
+    fn mov_card_to_board(
+        &self,
+        card_id: &str,
+        board_id: &str,
+        card_list: &str,
+        pos: &str,
+    ) -> Result<Card, FlowError> {
+        if self.state.borrow().board_of_list(card_list).as_deref() != Some(board_id) {
+            return Err(FlowError::HttpError(
+                400,
+                format!("the list {} is not on the board {}", card_list, board_id),
+            ));
+        }
+        self.mov_card(card_id, card_list, pos)
+    }
+
+    fn update_card_dsc(&self, card_id: &str, desc: &str) -> Result<Card, FlowError> {
+        let mut st = self.state.borrow_mut();
+        let card = st.card_mut(card_id)?;
+        card.desc = desc.to_string();
+        Ok(card.clone())
+    }
+
+    fn archive_card(&self, card_id: &str) -> Result<Card, FlowError> {
+        let mut st = self.state.borrow_mut();
+        let card = st.card_mut(card_id)?;
+        card.closed = true;
+        Ok(card.clone())
+    }
+
+    fn unarchive_card(&self, card_id: &str) -> Result<Card, FlowError> {
+        let mut st = self.state.borrow_mut();
+        let card = st.card_mut(card_id)?;
+        card.closed = false;
+        Ok(card.clone())
+    }
+
+    fn delete_card(&self, card_id: &str) -> Result<(), FlowError> {
+        let mut st = self.state.borrow_mut();
+        st.card_mut(card_id)?;
+        st.cards.retain(|c| c.id != card_id);
+        Ok(())
+    }
+
+    fn add_label(&self, card_id: &str, label_id: &str) -> Result<(), FlowError> {
+        let mut st = self.state.borrow_mut();
+        let card = st.card_mut(card_id)?;
+        if !card.id_labels.iter().any(|l| l == label_id) {
+            card.id_labels.push(label_id.to_string());
+        }
+        Ok(())
+    }
+
+    fn remove_label(&self, card_id: &str, label_id: &str) -> Result<(), FlowError> {
+        let mut st = self.state.borrow_mut();
+        st.card_mut(card_id)?.id_labels.retain(|l| l != label_id);
+        Ok(())
+    }
+
+    fn create_label(
+        &self,
+        board_id: &str,
+        name: &str,
+        color: Option<&str>,
+    ) -> Result<Label, FlowError> {
+        let mut st = self.state.borrow_mut();
+        let label = Label {
+            id: st.next_id("label"),
+            name: name.to_string(),
+            color: color.map(ToString::to_string),
+        };
+        st.labels.push((board_id.to_string(), label.clone()));
+        Ok(label)
+    }
+
+    /// Takes the label off the cards as well.
+    fn delete_label(&self, label_id: &str) -> Result<(), FlowError> {
+        let mut st = self.state.borrow_mut();
+        if !st.labels.iter().any(|(_, l)| l.id == label_id) {
+            return Err(not_found("label", label_id));
+        }
+        st.labels.retain(|(_, l)| l.id != label_id);
+        for card in st.cards.iter_mut() {
+            card.id_labels.retain(|l| l != label_id);
+        }
+        Ok(())
+    }
+}
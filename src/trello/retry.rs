@@ -0,0 +1,264 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The retry and rate limit settings, read from the `retry` section of the credentials file.
+/// Trello allows 100 requests per 10 seconds per token.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub requests: usize,
+    pub window_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+            requests: 100,
+            window_ms: 10_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with jitter: a random delay between a half and the whole of
+    /// `base * 2^attempt`, capped by `max_delay_ms`.
+    pub fn backoff<R: Rng>(&self, attempt: u32, rand: &mut R) -> Duration {
+        let exp = self
+            .base_delay_ms
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max_delay_ms);
+        Duration::from_millis(exp / 2 + rand.gen_range(0..=exp - exp / 2))
+    }
+
+    /// Decides whether a failed request should be sent again and after what delay.
+    /// 429 is always retried since trello did not process the request, after its Retry-After
+    /// capped by the maximal delay, server and transport failures are retried only for idempotent requests.
+    pub fn retry_delay<R: Rng>(
+        &self,
+        err: &ureq::Error,
+        attempt: u32,
+        idempotent: bool,
+        rand: &mut R,
+    ) -> Option<Duration> {
+        if attempt >= self.attempts {
+            return None;
+        }
+        match err {
+            ureq::Error::Status(429, resp) => Some(
+                resp.header("Retry-After")
+                    .and_then(|s| s.trim().parse::<u64>().ok())
+                    .map(|secs| {
+                        Duration::from_secs(secs).min(Duration::from_millis(self.max_delay_ms))
+                    })
+                    .unwrap_or_else(|| self.backoff(attempt, rand)),
+            ),
+            ureq::Error::Status(code, _) if *code >= 500 && idempotent => {
+                Some(self.backoff(attempt, rand))
+            }
+            ureq::Error::Transport(_) if idempotent => Some(self.backoff(attempt, rand)),
+            _ => None,
+        }
+    }
+
+    pub fn limiter(&self) -> RateLimiter {
+        RateLimiter::new(self.requests, Duration::from_millis(self.window_ms))
+    }
+
+    /// The limiter of the token shared by all the connectors of the process,
+    /// the budget is counted per token by trello. The first policy of the token is kept.
+    pub fn shared_limiter(&self, token: &str) -> Arc<Mutex<RateLimiter>> {
+        static LIMITERS: OnceLock<Mutex<HashMap<String, Arc<Mutex<RateLimiter>>>>> =
+            OnceLock::new();
+        LIMITERS
+            .get_or_init(Default::default)
+            .lock()
+            .expect("the rate limiters should not be poisoned")
+            .entry(token.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(self.limiter())))
+            .clone()
+    }
+}
+
+/// Keeps the moments of the requests sent during the last window
+/// and holds the next request back when the budget is spent.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests: usize,
+    window: Duration,
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(requests: usize, window: Duration) -> Self {
+        Self {
+            requests: requests.max(1),
+            window,
+            sent: VecDeque::new(),
+        }
+    }
+
+    fn delay(&mut self, now: Instant) -> Duration {
+        while let Some(first) = self.sent.front() {
+            if now.duration_since(*first) >= self.window {
+                self.sent.pop_front();
+            } else {
+                break;
+            }
+        }
+        // the moments may lie ahead when the requests waiting for their slots are registered
+        match self.sent.len().checked_sub(self.requests) {
+            Some(leaving) => (self.sent[leaving] + self.window).saturating_duration_since(now),
+            None => Duration::ZERO,
+        }
+    }
+
+    fn register(&mut self, now: Instant) {
+        self.sent.push_back(now);
+    }
+
+    /// Blocks until the next request fits into the budget.
+    /// The slot of the request is registered under the lock and the wait happens after releasing it,
+    /// so the other threads sharing the limiter take the next slots meanwhile.
+    pub fn acquire(limiter: &Mutex<RateLimiter>) {
+        let delay = {
+            let mut limiter = limiter
+                .lock()
+                .expect("the rate limiter should not be poisoned");
+            let now = Instant::now();
+            let delay = limiter.delay(now);
+            limiter.register(now + delay);
+            delay
+        };
+        if !delay.is_zero() {
+            info!("the request budget is spent, wait for {:?}", delay);
+            thread::sleep(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{RateLimiter, RetryPolicy};
+
+    fn status(code: u16, retry_after: Option<&str>) -> ureq::Error {
+        let resp = match retry_after {
+            Some(v) => format!("HTTP/1.1 {} Error\r\nRetry-After: {}\r\n\r\n", code, v),
+            None => format!("HTTP/1.1 {} Error\r\n\r\n", code),
+        };
+        ureq::Error::Status(code, resp.parse().unwrap())
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy::default();
+        let mut rand = StdRng::seed_from_u64(1);
+        for attempt in 0..10 {
+            let exp = (500 * 2u64.pow(attempt)).min(30_000);
+            let d = policy.backoff(attempt, &mut rand).as_millis() as u64;
+            assert!(d >= exp / 2 && d <= exp, "{} is out of {}", d, exp);
+        }
+    }
+
+    #[test]
+    fn retry_delay() {
+        let policy = RetryPolicy::default();
+        let mut rand = StdRng::seed_from_u64(1);
+
+        assert_eq!(
+            policy.retry_delay(&status(429, Some("7")), 0, false, &mut rand),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            policy.retry_delay(&status(429, Some("3600")), 0, false, &mut rand),
+            Some(Duration::from_secs(30))
+        );
+        assert!(policy
+            .retry_delay(&status(429, None), 0, false, &mut rand)
+            .is_some());
+        assert!(policy
+            .retry_delay(&status(503, None), 1, true, &mut rand)
+            .is_some());
+        assert!(policy
+            .retry_delay(&status(503, None), 1, false, &mut rand)
+            .is_none());
+        assert!(policy
+            .retry_delay(&status(401, None), 0, true, &mut rand)
+            .is_none());
+        assert!(policy
+            .retry_delay(&status(429, None), 3, true, &mut rand)
+            .is_none());
+    }
+
+    #[test]
+    fn shared_limiter() {
+        let policy = RetryPolicy::default();
+        let limiter = policy.shared_limiter("shared-limiter-token");
+        assert!(Arc::ptr_eq(
+            &limiter,
+            &policy.shared_limiter("shared-limiter-token")
+        ));
+        assert!(!Arc::ptr_eq(
+            &limiter,
+            &policy.shared_limiter("shared-limiter-other-token")
+        ));
+    }
+
+    #[test]
+    fn limiter() {
+        let mut limiter = RateLimiter::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert_eq!(limiter.delay(start), Duration::ZERO);
+        limiter.register(start);
+        assert_eq!(limiter.delay(start), Duration::ZERO);
+        limiter.register(start + Duration::from_secs(2));
+
+        assert_eq!(
+            limiter.delay(start + Duration::from_secs(4)),
+            Duration::from_secs(6)
+        );
+        assert_eq!(
+            limiter.delay(start + Duration::from_secs(10)),
+            Duration::ZERO
+        );
+
+        // the waiting requests take the next slots
+        limiter.register(start + Duration::from_secs(10));
+        assert_eq!(
+            limiter.delay(start + Duration::from_secs(4)),
+            Duration::from_secs(8)
+        );
+    }
+
+    #[test]
+    fn policy_from_yaml() {
+        let policy: RetryPolicy = serde_yaml::from_str("attempts: 5").unwrap();
+        assert_eq!(
+            policy,
+            RetryPolicy {
+                attempts: 5,
+                ..RetryPolicy::default()
+            }
+        );
+    }
+}